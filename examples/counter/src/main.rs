@@ -23,6 +23,8 @@ fn App() -> impl Component {
 }
 
 fn main() {
-    run_app(App);
+    AppBuilder::new(App)
+        .bind('q', Action::Quit)
+        .run();
 }
 
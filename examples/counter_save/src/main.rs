@@ -56,6 +56,8 @@ fn App() -> impl Component {
 }
 
 fn main() {
-    run_app(App);
+    AppBuilder::new(App)
+        .bind('q', Action::Quit)
+        .run();
 }
 
@@ -140,7 +140,25 @@ impl Element {
 
         let props_name = syn::Ident::new(&format!("{}Props", name), name.span());
 
-        let mut fields: Vec<proc_macro2::TokenStream> = self.attrs.iter().map(|attr| {
+        let mut ctx = quote! { ctx.clone() };
+
+        let mut fields: Vec<proc_macro2::TokenStream> = self.attrs.iter().filter_map(|attr| {
+            if attr.prefix.is_some() {
+                return Some(attr);
+            }
+
+            let value = match &attr.value {
+                AttributeValue::Literal(lit) => quote! { #lit },
+                AttributeValue::Expr(expr) => quote! { (#expr) },
+            };
+
+            if attr.name == "key" {
+                ctx = quote! { ctx.keyed(#value) };
+                return None;
+            }
+
+            Some(attr)
+        }).map(|attr| {
             let key = &attr.name;
             let prefix = &attr.prefix;
 
@@ -154,8 +172,19 @@ impl Element {
                 },
                 AttributeValue::Expr(expr) => {
                     if let Some(prefix) = prefix {
-                        if prefix == "on" {
-                            let combined = format_ident!("{}_{}", prefix, key);
+                        // capture:click={..} handles the click on the way down
+                        let combined = match prefix.to_string().as_str() {
+                            "on" => Some(format_ident!("on_{}", key)),
+                            "capture" => Some(format_ident!("on_{}_capture", key)),
+                            _ => None,
+                        };
+
+                        if let Some(combined) = combined {
+                            // Click handlers may or may not take the event
+                            if key == "click" {
+                                return quote! { #combined: Some((#expr).into_click_handler()) };
+                            }
+
                             return quote! { #combined: Some(Box::new(#expr)) };
                         }
                     }
@@ -169,6 +198,8 @@ impl Element {
             }
         }).collect();
 
+        // Children are passed as a closure so components like Provider can
+        // build them once they are ready for them
         match &self.children {
             Children::ElementList(elements) => {
                 let child_exprs = elements.iter().map(|child| {
@@ -181,9 +212,11 @@ impl Element {
                 if !elements.is_empty() {
                     fields.push(
                         quote! {
-                            children: vec![
-                                #(#child_exprs),*
-                            ]
+                            children: (|| -> Vec<Box<dyn Component>> {
+                                vec![
+                                    #(#child_exprs),*
+                                ]
+                            }).into_children()
                         }
                     );
                 }
@@ -191,19 +224,30 @@ impl Element {
             Children::Expr(expr) => {
                 fields.push(
                     quote! {
-                        children: #expr
+                        children: (|| -> Vec<Box<dyn Component>> { #expr }).into_children()
                     }
                 );
             },
         }
 
-        quote! {
-            #name(
-                ctx.clone(),
-                #props_name {
+        // Unset props are defaulted. When every prop is given that leaves the
+        // update with nothing to fill, which is fine in generated code.
+        let props = quote! {
+            {
+                #[allow(clippy::needless_update)]
+                let props = #props_name {
                     #(#fields,)*
                     ..Default::default()
-                }
+                };
+
+                props
+            }
+        };
+
+        quote! {
+            #name(
+                #ctx,
+                #props
             )
         }
     }
@@ -263,6 +307,8 @@ pub fn component(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
         #[allow(non_snake_case)]
         #vis fn #fn_name(ctx: StateContext, props: #props_struct_name) -> impl Component {
+            let _scope_guard = ctx.scope(concat!(module_path!(), "::", stringify!(#fn_name)));
+            let ctx = ctx.unkeyed();
             let #props_struct_name { #(#param_idents),* } = props;
 
             #block
@@ -1,8 +1,14 @@
-use std::{io::{stdout, Stdout}, time::Duration};
+use std::time::Duration;
 
-use crossterm::{cursor::{Hide, Show}, event::{DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent, MouseEvent, MouseEventKind}, execute, terminal::{disable_raw_mode, enable_raw_mode, size, EnterAlternateScreen, LeaveAlternateScreen}};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 
-use crate::{component::{Component, ComponentEvent}, events::{Event, EventManager}, renderer::Renderer, state::StateContext};
+use crate::{backend::{Backend, CrosstermBackend}, component::{event::dispatch, mouse::MouseInput, Component, ComponentEvent, Rect}, events::{Event, EventManager}, keymap::{Action, KeyMatch, KeySequence, Keymap}, renderer::Renderer, state::StateContext};
+
+// Bounds how often a frame re-renders when rendering itself keeps marking state
+// dirty, so a component setting state unconditionally can't hang the app.
+const MAX_RENDER_PASSES: usize = 16;
+
+pub(crate) type RootFn = Box<dyn Fn(StateContext) -> Box<dyn Component + 'static>>;
 
 pub fn run_app<F, P, C>(f: F)
 where
@@ -10,87 +16,302 @@ where
     P: Default,
     C: Component + 'static,
 {
-    let root_fn = Box::new(move |ctx| Box::new(f(ctx, Default::default())) as Box<dyn Component>);
+    AppBuilder::new(f).run();
+}
+
+pub fn run_app_with_backend<F, P, C, B>(f: F, backend: B)
+where
+    F: Fn(StateContext, P) -> C + 'static,
+    P: Default,
+    C: Component + 'static,
+    B: Backend,
+{
+    AppBuilder::new(f).run_with_backend(backend);
+}
+
+// Configures an app before running it. Starts from the default keymap, where
+// Ctrl-C quits and Tab and Shift-Tab move focus.
+pub struct AppBuilder {
+    root_fn: RootFn,
+    keymap: Keymap,
+    click_button: Option<MouseButton>,
+}
+
+impl AppBuilder {
+    pub fn new<F, P, C>(f: F) -> Self
+    where
+        F: Fn(StateContext, P) -> C + 'static,
+        P: Default,
+        C: Component + 'static,
+    {
+        AppBuilder {
+            root_fn: root_fn(f),
+            keymap: Keymap::default(),
+            click_button: None,
+        }
+    }
+
+    pub fn bind(mut self, keys: impl Into<KeySequence>, action: Action) -> Self {
+        self.keymap.bind(keys, action);
+        self
+    }
+
+    pub fn unbind(mut self, keys: impl Into<KeySequence>) -> Self {
+        self.keymap.unbind(keys);
+        self
+    }
+
+    pub fn keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    // Only presses of `button` click components. By default any button does,
+    // so a right click reaches both on:contextmenu and on:click.
+    pub fn click_button(mut self, button: MouseButton) -> Self {
+        self.click_button = Some(button);
+        self
+    }
+
+    pub fn run(self) {
+        self.run_with_backend(CrosstermBackend::default());
+    }
 
-    App::new(root_fn)
-        .run()
-        .unwrap();
+    pub fn run_with_backend<B: Backend>(self, backend: B) {
+        self.build(backend)
+            .run()
+            .unwrap();
+    }
+
+    pub(crate) fn build<B: Backend>(self, backend: B) -> App<B> {
+        App::new(self.root_fn, self.keymap, self.click_button, backend)
+    }
+}
+
+pub(crate) fn root_fn<F, P, C>(f: F) -> RootFn
+where
+    F: Fn(StateContext, P) -> C + 'static,
+    P: Default,
+    C: Component + 'static,
+{
+    Box::new(move |ctx| Box::new(f(ctx, Default::default())) as Box<dyn Component>)
 }
 
 
-struct App {
-    root_fn: Box<dyn Fn(StateContext) -> Box<dyn Component + 'static>>,
+pub(crate) struct App<B: Backend> {
+    root_fn: RootFn,
+    root: Option<Box<dyn Component>>,
     renderer: Renderer,
     event_manager: EventManager,
     state_context: StateContext,
-    stdout: Stdout,
+    keymap: Keymap,
+    click_button: Option<MouseButton>,
+    mouse_position: Option<(usize, usize)>,
+    drag_start: Option<(usize, usize)>,
+    backend: B,
 }
 
-impl App {
-    pub fn new(root_fn: Box<dyn Fn(StateContext) -> Box<dyn Component + 'static>>) -> Self {
+impl<B: Backend> App<B> {
+    pub fn new(root_fn: RootFn, keymap: Keymap, click_button: Option<MouseButton>, backend: B) -> Self {
+        let event_manager = EventManager::new();
+        let state_context = StateContext::new();
+        state_context.set_event_sender(event_manager.sender());
+
         App {
             root_fn,
+            root: None,
             renderer: Renderer::new(),
-            event_manager: EventManager::new(Duration::from_millis(33)),
-            state_context: StateContext::new(),
-            stdout: stdout()
+            event_manager,
+            state_context,
+            keymap,
+            click_button,
+            mouse_position: None,
+            drag_start: None,
+            backend,
         }
     }
 
     pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        enable_raw_mode()?;
-        execute!(self.stdout, EnterAlternateScreen)?;
-        execute!(self.stdout, EnableMouseCapture)?;
+        self.backend.enable_raw_mode()?;
+        self.backend.enter_alternate_screen()?;
+        self.backend.enable_mouse_capture()?;
+        self.backend.enable_bracketed_paste()?;
+        self.backend.enable_focus_change()?;
+        self.backend.hide_cursor()?;
+
+        self.event_manager.listen(Duration::from_millis(33));
+        self.mount()?;
+
+        loop {
+            let event = self.event_manager.next()?;
+
+            if !self.handle_event(event) {
+                break;
+            }
+
+            self.draw()?;
+        }
+
+        self.backend.show_cursor()?;
+        self.backend.disable_focus_change()?;
+        self.backend.disable_bracketed_paste()?;
+        self.backend.disable_mouse_capture()?;
+        self.backend.leave_alternate_screen()?;
+        self.backend.disable_raw_mode()?;
+        Ok(())
+    }
 
-        let (width, height) = size()?;
+    pub fn mount(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (width, height) = self.backend.size()?;
         self.resize(width, height);
-        execute!(self.stdout, Hide)?;
 
-        let mut root = (self.root_fn)(self.state_context.clone());
+        self.build_root();
+        self.draw()
+    }
+
+    // Returns false once the app has been asked to quit.
+    pub fn handle_event(&mut self, event: Event) -> bool {
+        match event {
+            Event::Key(key) => self.handle_key(key),
+            Event::Mouse(mouse) => self.handle_mouse(mouse),
+            Event::Paste(text) => self.event_manager.send(Event::Component(ComponentEvent::Paste(text))),
+            Event::FocusGained => self.state_context.set_terminal_focus(true),
+            Event::FocusLost => self.state_context.set_terminal_focus(false),
+            Event::Tick => {
+                let expired = self.keymap.expire(self.state_context.now());
+                self.handle_key_matches(expired);
 
-        loop {
-            match self.event_manager.next()? {
-                Event::Key(key) => self.handle_key(key),
-                Event::Mouse(mouse) => self.handle_mouse(mouse),
-                Event::Tick => {},
-                Event::Resize(w, h) => self.resize(w, h),
-                Event::Component(component_event) => root.propagate_event(&component_event),
-                Event::Quit => break,
+                self.state_context.batch(|| self.state_context.run_timers());
+            },
+            Event::Resize(w, h) => self.resize(w, h),
+            Event::Component(component_event) => {
+                if let Some(root) = &mut self.root {
+                    self.state_context.batch(|| dispatch(root.as_mut(), &self.state_context, &component_event));
+                }
+            },
+            Event::State(update) => {
+                self.state_context.batch(|| self.state_context.apply_update(update));
+            },
+            Event::Quit => return false,
+        }
+
+        true
+    }
+
+    pub fn draw(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        for _ in 0..MAX_RENDER_PASSES {
+            if !self.state_context.take_dirty() {
+                break;
             }
 
+            self.build_root();
+        }
+
+        if let Some(root) = &mut self.root {
+            let (width, height) = self.renderer.size();
+
+            root.arrange(Rect { x: 0, y: 0, width, height });
             root.render(&mut self.renderer);
-            self.renderer.render(&mut self.stdout)?;
         }
 
-        execute!(self.stdout, Show)?;
-        execute!(self.stdout, DisableMouseCapture)?;
-        execute!(self.stdout, LeaveAlternateScreen)?;
-        disable_raw_mode()?;
-        Ok(())
+        self.renderer.render(&mut self.backend)
+    }
+
+    // Runs the root component function again, rebuilding the whole tree
+    // whenever any state was set. Hook storage lives in the StateContext
+    // scopes, so components pick up their existing state.
+    fn build_root(&mut self) {
+        let ctx = self.state_context.clone();
+        let _scope_guard = ctx.scope("root");
+
+        let root = (self.root_fn)(ctx.clone());
+
+        let mut focus_order = vec![];
+        root.collect_focusable(&mut focus_order);
+        self.state_context.focus_manager().borrow_mut().set_order(focus_order);
+
+        self.root = Some(root);
+    }
+
+    pub fn state_context(&self) -> &StateContext {
+        &self.state_context
+    }
+
+    pub fn event_manager(&self) -> &EventManager {
+        &self.event_manager
     }
 
-    fn resize(&mut self, w: u16, h: u16) {
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn resize(&mut self, w: u16, h: u16) {
         self.renderer.resize(w as usize, h as usize);
     }
 
     fn handle_key(&mut self, key_event: KeyEvent) {
-        match key_event {
-            KeyEvent { code: KeyCode::Char('q'), .. } => {
-                self.event_manager.send(Event::Quit);
+        let matches = self.keymap.press(key_event, self.state_context.now());
+        self.handle_key_matches(matches);
+    }
+
+    // Bound keys run their action, anything else goes to the components.
+    fn handle_key_matches(&mut self, matches: Vec<KeyMatch>) {
+        for key_match in matches {
+            match key_match {
+                KeyMatch::Action(action) => self.run_action(action),
+                KeyMatch::Pending => {},
+                KeyMatch::None(key_event) => {
+                    self.event_manager.send(Event::Component(
+                        ComponentEvent::Key(key_event)
+                    ));
+                },
             }
-            _ => {}
+        }
+    }
+
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.event_manager.send(Event::Quit),
+            Action::FocusNext => self.state_context.focus_manager().borrow_mut().focus_next(),
+            Action::FocusPrevious => self.state_context.focus_manager().borrow_mut().focus_previous(),
+            Action::Custom(name) => self.state_context.batch(|| self.state_context.run_action(name)),
         }
     }
 
     fn handle_mouse(&mut self, mouse_event: MouseEvent) {
-        match mouse_event {
-            MouseEvent { kind: MouseEventKind::Down(_), row, column, .. } => {
-                self.event_manager.send(Event::Component(
-                    ComponentEvent::OnClick(column as usize, row as usize)
-                ));
-            },
-            _ => {}
+        let MouseEvent { kind, column, row, modifiers } = mouse_event;
+        let position = (column as usize, row as usize);
+
+        if let MouseEventKind::Down(_) = kind {
+            self.drag_start = Some(position);
+        }
+
+        self.event_manager.send(Event::Component(ComponentEvent::Mouse(MouseInput {
+            kind,
+            column: position.0,
+            row: position.1,
+            modifiers,
+            previous: self.mouse_position,
+            drag_start: self.drag_start,
+        })));
+
+        if let MouseEventKind::Down(button) = kind
+            && self.click_button.is_none_or(|click_button| click_button == button)
+        {
+            self.event_manager.send(Event::Component(
+                ComponentEvent::OnClick(position.0, position.1)
+            ));
         }
+
+        if let MouseEventKind::Up(_) = kind {
+            self.drag_start = None;
+        }
+
+        self.mouse_position = Some(position);
     }
 
     // fn render(&mut self, root: &mut dyn Component) -> Result<(), Box<dyn std::error::Error>> {
@@ -100,3 +321,8 @@ impl App {
     // }
 }
 
+impl<B: Backend> Drop for App<B> {
+    fn drop(&mut self) {
+        self.state_context.dispose();
+    }
+}
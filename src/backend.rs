@@ -0,0 +1,405 @@
+use std::io::{self, stdout, Stdout, Write};
+
+use crossterm::{cursor::{Hide, MoveTo, Show}, event::{DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste, EnableFocusChange, EnableMouseCapture}, execute, style::{Attribute, Attributes, Color, ContentStyle, Print, SetAttributes, SetBackgroundColor, SetForegroundColor, SetUnderlineColor}, terminal::{self, EnterAlternateScreen, LeaveAlternateScreen}, QueueableCommand};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::screen_buffer::{grapheme_width, BufferCell, ScreenBuffer};
+
+pub trait Backend {
+    fn size(&self) -> io::Result<(u16, u16)>;
+
+    fn enable_raw_mode(&mut self) -> io::Result<()>;
+    fn disable_raw_mode(&mut self) -> io::Result<()>;
+    fn enter_alternate_screen(&mut self) -> io::Result<()>;
+    fn leave_alternate_screen(&mut self) -> io::Result<()>;
+    fn enable_mouse_capture(&mut self) -> io::Result<()>;
+    fn disable_mouse_capture(&mut self) -> io::Result<()>;
+    fn enable_bracketed_paste(&mut self) -> io::Result<()>;
+    fn disable_bracketed_paste(&mut self) -> io::Result<()>;
+    fn enable_focus_change(&mut self) -> io::Result<()>;
+    fn disable_focus_change(&mut self) -> io::Result<()>;
+
+    fn hide_cursor(&mut self) -> io::Result<()>;
+    fn show_cursor(&mut self) -> io::Result<()>;
+    fn move_cursor(&mut self, x: u16, y: u16) -> io::Result<()>;
+
+    fn set_foreground_color(&mut self, color: Color) -> io::Result<()>;
+    fn set_background_color(&mut self, color: Color) -> io::Result<()>;
+    fn set_underline_color(&mut self, color: Color) -> io::Result<()>;
+    fn set_attributes(&mut self, attributes: Attributes) -> io::Result<()>;
+    fn print(&mut self, content: &str) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+pub struct CrosstermBackend<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CrosstermBackend<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl Default for CrosstermBackend<Stdout> {
+    fn default() -> Self {
+        Self::new(stdout())
+    }
+}
+
+impl<W: Write> Backend for CrosstermBackend<W> {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        terminal::disable_raw_mode()
+    }
+
+    fn enter_alternate_screen(&mut self) -> io::Result<()> {
+        execute!(self.writer, EnterAlternateScreen)
+    }
+
+    fn leave_alternate_screen(&mut self) -> io::Result<()> {
+        execute!(self.writer, LeaveAlternateScreen)
+    }
+
+    fn enable_mouse_capture(&mut self) -> io::Result<()> {
+        execute!(self.writer, EnableMouseCapture)
+    }
+
+    fn disable_mouse_capture(&mut self) -> io::Result<()> {
+        execute!(self.writer, DisableMouseCapture)
+    }
+
+    fn enable_bracketed_paste(&mut self) -> io::Result<()> {
+        execute!(self.writer, EnableBracketedPaste)
+    }
+
+    fn disable_bracketed_paste(&mut self) -> io::Result<()> {
+        execute!(self.writer, DisableBracketedPaste)
+    }
+
+    fn enable_focus_change(&mut self) -> io::Result<()> {
+        execute!(self.writer, EnableFocusChange)
+    }
+
+    fn disable_focus_change(&mut self) -> io::Result<()> {
+        execute!(self.writer, DisableFocusChange)
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        execute!(self.writer, Hide)
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        execute!(self.writer, Show)
+    }
+
+    fn move_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.writer.queue(MoveTo(x, y))?;
+        Ok(())
+    }
+
+    fn set_foreground_color(&mut self, color: Color) -> io::Result<()> {
+        self.writer.queue(SetForegroundColor(color))?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) -> io::Result<()> {
+        self.writer.queue(SetBackgroundColor(color))?;
+        Ok(())
+    }
+
+    fn set_underline_color(&mut self, color: Color) -> io::Result<()> {
+        self.writer.queue(SetUnderlineColor(color))?;
+        Ok(())
+    }
+
+    fn set_attributes(&mut self, attributes: Attributes) -> io::Result<()> {
+        // SetAttributes only turns attributes on, so clear every one first. The
+        // colours are left alone, unlike with Attribute::Reset.
+        let cleared = [
+            Attribute::NormalIntensity,
+            Attribute::NoItalic,
+            Attribute::NoUnderline,
+            Attribute::NoBlink,
+            Attribute::NoReverse,
+            Attribute::NoHidden,
+            Attribute::NotCrossedOut,
+            Attribute::NotFramedOrEncircled,
+            Attribute::NotOverLined,
+        ];
+
+        self.writer.queue(SetAttributes(cleared.as_slice().into()))?;
+        self.writer.queue(SetAttributes(attributes))?;
+        Ok(())
+    }
+
+    fn print(&mut self, content: &str) -> io::Result<()> {
+        self.writer.queue(Print(content))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// Behaves like a terminal that keeps everything it is sent in a ScreenBuffer,
+// so the result of a render can be inspected without a TTY.
+pub struct TestBackend {
+    buffer: ScreenBuffer,
+    width: u16,
+    height: u16,
+    cursor: (usize, usize),
+    style: ContentStyle,
+
+    raw_mode: bool,
+    alternate_screen: bool,
+    mouse_capture: bool,
+    bracketed_paste: bool,
+    focus_change: bool,
+    cursor_visible: bool,
+}
+
+impl TestBackend {
+    pub fn new(width: u16, height: u16) -> Self {
+        let mut buffer = ScreenBuffer::new();
+        buffer.resize(width as usize, height as usize);
+
+        Self {
+            buffer,
+            width,
+            height,
+            cursor: (0, 0),
+            style: ContentStyle::new(),
+
+            raw_mode: false,
+            alternate_screen: false,
+            mouse_capture: false,
+            bracketed_paste: false,
+            focus_change: false,
+            cursor_visible: true,
+        }
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.buffer.resize(width as usize, height as usize);
+        self.buffer.clear();
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn buffer(&self) -> &ScreenBuffer {
+        &self.buffer
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn is_raw_mode(&self) -> bool {
+        self.raw_mode
+    }
+
+    pub fn is_alternate_screen(&self) -> bool {
+        self.alternate_screen
+    }
+
+    pub fn is_mouse_capture(&self) -> bool {
+        self.mouse_capture
+    }
+
+    pub fn is_bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    pub fn is_focus_change(&self) -> bool {
+        self.focus_change
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+}
+
+impl Backend for TestBackend {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok((self.width, self.height))
+    }
+
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        self.raw_mode = true;
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        self.raw_mode = false;
+        Ok(())
+    }
+
+    fn enter_alternate_screen(&mut self) -> io::Result<()> {
+        self.alternate_screen = true;
+        Ok(())
+    }
+
+    fn leave_alternate_screen(&mut self) -> io::Result<()> {
+        self.alternate_screen = false;
+        Ok(())
+    }
+
+    fn enable_mouse_capture(&mut self) -> io::Result<()> {
+        self.mouse_capture = true;
+        Ok(())
+    }
+
+    fn disable_mouse_capture(&mut self) -> io::Result<()> {
+        self.mouse_capture = false;
+        Ok(())
+    }
+
+    fn enable_bracketed_paste(&mut self) -> io::Result<()> {
+        self.bracketed_paste = true;
+        Ok(())
+    }
+
+    fn disable_bracketed_paste(&mut self) -> io::Result<()> {
+        self.bracketed_paste = false;
+        Ok(())
+    }
+
+    fn enable_focus_change(&mut self) -> io::Result<()> {
+        self.focus_change = true;
+        Ok(())
+    }
+
+    fn disable_focus_change(&mut self) -> io::Result<()> {
+        self.focus_change = false;
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.cursor_visible = false;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.cursor_visible = true;
+        Ok(())
+    }
+
+    fn move_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.cursor = (x as usize, y as usize);
+        Ok(())
+    }
+
+    fn set_foreground_color(&mut self, color: Color) -> io::Result<()> {
+        self.style.foreground_color = Some(color);
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) -> io::Result<()> {
+        self.style.background_color = Some(color);
+        Ok(())
+    }
+
+    fn set_underline_color(&mut self, color: Color) -> io::Result<()> {
+        self.style.underline_color = Some(color);
+        Ok(())
+    }
+
+    fn set_attributes(&mut self, attributes: Attributes) -> io::Result<()> {
+        self.style.attributes = attributes;
+        Ok(())
+    }
+
+    fn print(&mut self, content: &str) -> io::Result<()> {
+        let (mut x, y) = self.cursor;
+
+        for grapheme in content.graphemes(true) {
+            let width = grapheme_width(grapheme);
+
+            if width > 0 {
+                self.buffer.set(x, y, BufferCell::grapheme(self.style, grapheme));
+                x += width;
+            }
+        }
+
+        self.cursor = (x, y);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes_output(steps: &[&[Attribute]]) -> String {
+        let mut backend = CrosstermBackend::new(Vec::new());
+
+        for attributes in steps {
+            backend.set_attributes((*attributes).into()).unwrap();
+        }
+
+        String::from_utf8(backend.writer).unwrap()
+    }
+
+    #[test]
+    fn setting_attributes_clears_every_previous_one() {
+        let all = [
+            Attribute::Bold,
+            Attribute::Dim,
+            Attribute::Italic,
+            Attribute::Underlined,
+            Attribute::SlowBlink,
+            Attribute::Reverse,
+            Attribute::Hidden,
+            Attribute::CrossedOut,
+            Attribute::Framed,
+            Attribute::OverLined,
+        ];
+
+        let mut terminal = ContentStyle::new();
+
+        // Applies the output the way a terminal would
+        for code in attributes_output(&[&all, &[]]).split('m').filter(|code| !code.is_empty()) {
+            let code = code.trim_start_matches("\x1b[");
+            let attribute = Attribute::iterator().find(|attribute| attribute.sgr() == code).unwrap();
+
+            match attribute {
+                Attribute::NormalIntensity => {
+                    terminal.attributes.unset(Attribute::Bold);
+                    terminal.attributes.unset(Attribute::Dim);
+                },
+                Attribute::NoItalic => terminal.attributes.unset(Attribute::Italic),
+                Attribute::NoUnderline => terminal.attributes.unset(Attribute::Underlined),
+                Attribute::NoBlink => terminal.attributes.unset(Attribute::SlowBlink),
+                Attribute::NoReverse => terminal.attributes.unset(Attribute::Reverse),
+                Attribute::NoHidden => terminal.attributes.unset(Attribute::Hidden),
+                Attribute::NotCrossedOut => terminal.attributes.unset(Attribute::CrossedOut),
+                Attribute::NotFramedOrEncircled => terminal.attributes.unset(Attribute::Framed),
+                Attribute::NotOverLined => terminal.attributes.unset(Attribute::OverLined),
+                attribute => terminal.attributes.set(attribute),
+            }
+        }
+
+        assert!(terminal.attributes.is_empty(), "{:?}", terminal.attributes);
+    }
+
+    #[test]
+    fn setting_attributes_keeps_the_colours() {
+        let output = attributes_output(&[&[Attribute::Bold], &[Attribute::Italic]]);
+
+        assert!(!output.contains(&format!("\x1b[{}m", Attribute::Reset.sgr())), "{output:?}");
+    }
+}
@@ -1,20 +1,46 @@
 use crate::prelude::*;
 use crate::marcos::component;
+use crossterm::event::KeyEvent;
 
 #[component]
 pub fn Button(
     border: bool, 
     width: StackWidth, 
     value: String, 
-    on_click: Option<Box<dyn FnMut()>>,
+    on_click: Option<Handler>,
+    on_click_capture: Option<Handler>,
+    on_key: Option<Handler<KeyEvent>>,
+    on_keydown: Option<Handler<KeyEvent>>,
+    on_key_capture: Option<Handler<KeyEvent>>,
+    on_paste: Option<Handler<String>>,
+    on_hover: Option<MouseHandler>,
+    on_leave: Option<MouseHandler>,
+    on_scroll: Option<MouseHandler>,
+    on_drag: Option<MouseHandler>,
+    on_release: Option<MouseHandler>,
+    on_contextmenu: Option<MouseHandler>,
     padding: Sides,
+    style: Style,
 ) -> impl Component {
     ui! {
         <Stack
             width={width}
             border={border}
             on_click={on_click}
+            on_click_capture={on_click_capture}
+            on_key={on_key}
+            on_keydown={on_keydown}
+            on_key_capture={on_key_capture}
+            on_paste={on_paste}
+            on_hover={on_hover}
+            on_leave={on_leave}
+            on_scroll={on_scroll}
+            on_drag={on_drag}
+            on_release={on_release}
+            on_contextmenu={on_contextmenu}
             padding={padding}
+            focusable={true}
+            style={style}
         >
             <Text
                 width={StackWidth::Content}
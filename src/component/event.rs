@@ -0,0 +1,648 @@
+use std::{cell::Cell, ops::Deref, rc::Rc};
+
+use crossterm::event::{KeyCode, KeyEventKind, MouseEventKind};
+
+use crate::{component::{mouse::MouseInput, Component, ComponentEvent}, state::StateContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Capture,
+    Target,
+    Bubble,
+}
+
+#[derive(Default)]
+struct Propagation {
+    stopped: Cell<bool>,
+    default_prevented: Cell<bool>,
+    handled: Cell<bool>,
+}
+
+// What a handler receives: the event data and the phase it's being handled
+// in. The propagation flags are shared by every handler the event reaches.
+pub struct UiEvent<T = ()> {
+    data: T,
+    phase: Phase,
+    propagation: Rc<Propagation>,
+}
+
+impl<T> UiEvent<T> {
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    // Keeps the event from reaching any further components.
+    pub fn stop_propagation(&self) {
+        self.propagation.stopped.set(true);
+    }
+
+    // Skips what the app does after the event has propagated, like focusing
+    // the clicked component, clicking the focused one on Enter or moving focus
+    // with the arrow keys.
+    pub fn prevent_default(&self) {
+        self.propagation.default_prevented.set(true);
+    }
+
+    pub fn is_propagation_stopped(&self) -> bool {
+        self.propagation.stopped.get()
+    }
+
+    pub fn is_default_prevented(&self) -> bool {
+        self.propagation.default_prevented.get()
+    }
+
+    // Whether any component had a handler for the event.
+    pub(crate) fn is_handled(&self) -> bool {
+        self.propagation.handled.get()
+    }
+
+    // Capture handlers run in the capture phase and at the target, bubble
+    // handlers at the target and in the bubble phase.
+    pub(crate) fn captures(&self) -> bool {
+        self.phase != Phase::Bubble
+    }
+
+    pub(crate) fn bubbles(&self) -> bool {
+        self.phase != Phase::Capture
+    }
+}
+
+impl<T> Deref for UiEvent<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+pub type Handler<T = ()> = Box<dyn FnMut(UiEvent<T>)>;
+
+// Click handlers can take the event or, as before events propagated, nothing
+// at all. The marker only tells the two impls apart.
+pub trait IntoClickHandler<M> {
+    fn into_click_handler(self) -> Handler;
+}
+
+pub struct WithEvent;
+pub struct WithoutEvent;
+
+impl<F> IntoClickHandler<WithEvent> for F
+where
+    F: FnMut(UiEvent) + 'static,
+{
+    fn into_click_handler(self) -> Handler {
+        Box::new(self)
+    }
+}
+
+impl<F> IntoClickHandler<WithoutEvent> for F
+where
+    F: FnMut() + 'static,
+{
+    fn into_click_handler(mut self) -> Handler {
+        Box::new(move |_| self())
+    }
+}
+
+// Calls the handler with its own data, sharing the propagation flags of the
+// event being dispatched.
+pub(crate) fn call<T>(handler: &mut Option<Handler<T>>, data: T, ui_event: &UiEvent) {
+    if let Some(handler) = handler {
+        ui_event.propagation.handled.set(true);
+
+        handler(UiEvent {
+            data,
+            phase: ui_event.phase,
+            propagation: ui_event.propagation.clone(),
+        });
+    }
+}
+
+// A path is the child index taken at each level, starting from the root.
+type Path = Vec<usize>;
+
+// Sends an event into the tree. Clicks and mouse events target the deepest
+// component under the mouse, keys and pastes the focused one. Each passes
+// through the target's ancestors on the way down and again on the way up.
+pub(crate) fn dispatch(root: &mut dyn Component, ctx: &StateContext, event: &ComponentEvent) {
+    match event {
+        ComponentEvent::OnClick(x, y) => {
+            if let Some(path) = hit_test(root, *x, *y) {
+                click(root, ctx, &path, event);
+            }
+        },
+        ComponentEvent::Key(key) => {
+            // With nothing focused only the root sees the key
+            let path = focus_path(root, ctx);
+            let ui_event = propagate(root, event, path.as_deref().unwrap_or_default());
+
+            if key.kind == KeyEventKind::Release || ui_event.is_default_prevented() {
+                return;
+            }
+
+            match key.code {
+                // Enter and Space click the focused component
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    if let Some(path) = path {
+                        let bounds = node(root, &path).bounds();
+                        propagate(root, &ComponentEvent::OnClick(bounds.x, bounds.y), &path);
+                    }
+                },
+                // Arrow keys move focus, unless a component handled them
+                KeyCode::Right | KeyCode::Down if !ui_event.is_handled() => {
+                    ctx.focus_manager().borrow_mut().focus_next();
+                },
+                KeyCode::Left | KeyCode::Up if !ui_event.is_handled() => {
+                    ctx.focus_manager().borrow_mut().focus_previous();
+                },
+                _ => {},
+            }
+        },
+        ComponentEvent::Paste(_) => {
+            let path = focus_path(root, ctx).unwrap_or_default();
+            propagate(root, event, &path);
+        },
+        ComponentEvent::Mouse(input) => {
+            enter_leave(root, input);
+
+            // Drags and the release ending them go to where the press was
+            let origin = match input.kind {
+                MouseEventKind::Moved => None,
+                MouseEventKind::Drag(_) => input.drag_start,
+                MouseEventKind::Up(_) => input.drag_start.or(Some((input.column, input.row))),
+                _ => Some((input.column, input.row)),
+            };
+
+            if let Some((x, y)) = origin
+                && let Some(path) = hit_test(root, x, y)
+            {
+                propagate(root, event, &path);
+            }
+        },
+        ComponentEvent::Hover(input) | ComponentEvent::Leave(input) => {
+            if let Some(path) = hit_test(root, input.column, input.row) {
+                target(root, event, &path);
+            }
+        },
+    }
+}
+
+fn click(root: &mut dyn Component, ctx: &StateContext, path: &[usize], event: &ComponentEvent) {
+    let ui_event = propagate(root, event, path);
+
+    if ui_event.is_default_prevented() {
+        return;
+    }
+
+    // Focuses the closest focusable component that was clicked
+    let focus_id = (0..=path.len()).rev()
+        .find_map(|depth| node(root, &path[..depth]).focus_id());
+
+    // Clicking outside every focusable component clears focus
+    match focus_id {
+        Some(id) => ctx.focus(id),
+        None => ctx.focus_manager().borrow_mut().focus(None),
+    }
+}
+
+fn propagate(root: &mut dyn Component, event: &ComponentEvent, path: &[usize]) -> UiEvent {
+    let mut ui_event = UiEvent {
+        data: (),
+        phase: Phase::Capture,
+        propagation: Rc::default(),
+    };
+
+    for depth in 0..path.len() {
+        node_mut(root, &path[..depth]).handle_event(event, &ui_event);
+
+        if ui_event.is_propagation_stopped() {
+            return ui_event;
+        }
+    }
+
+    ui_event.phase = Phase::Target;
+    node_mut(root, path).handle_event(event, &ui_event);
+
+    ui_event.phase = Phase::Bubble;
+
+    for depth in (0..path.len()).rev() {
+        if ui_event.is_propagation_stopped() {
+            break;
+        }
+
+        node_mut(root, &path[..depth]).handle_event(event, &ui_event);
+    }
+
+    ui_event
+}
+
+fn target(root: &mut dyn Component, event: &ComponentEvent, path: &[usize]) {
+    let ui_event = UiEvent {
+        data: (),
+        phase: Phase::Target,
+        propagation: Rc::default(),
+    };
+
+    node_mut(root, path).handle_event(event, &ui_event);
+}
+
+// Sends Leave to the components the mouse was over and no longer is, deepest
+// first, then Hover to the ones it has moved onto.
+fn enter_leave(root: &mut dyn Component, input: &MouseInput) {
+    let previous = input.previous.and_then(|(x, y)| hit_test(root, x, y));
+    let current = hit_test(root, input.column, input.row);
+
+    let shared = match (&previous, &current) {
+        (Some(previous), Some(current)) => {
+            1 + previous.iter().zip(current).take_while(|(a, b)| a == b).count()
+        },
+        _ => 0,
+    };
+
+    if let Some(previous) = previous {
+        for depth in (shared..=previous.len()).rev() {
+            target(root, &ComponentEvent::Leave(*input), &previous[..depth]);
+        }
+    }
+
+    if let Some(current) = current {
+        for depth in shared..=current.len() {
+            target(root, &ComponentEvent::Hover(*input), &current[..depth]);
+        }
+    }
+}
+
+// Finds the deepest component containing the point. Later children are drawn
+// over earlier ones, so they're tried first.
+fn hit_test(component: &dyn Component, x: usize, y: usize) -> Option<Path> {
+    if !component.bounds().contains(x, y) {
+        return None;
+    }
+
+    let child = component.children().iter().enumerate().rev()
+        .find_map(|(i, child)| {
+            hit_test(child.as_ref(), x, y).map(|mut path| {
+                path.insert(0, i);
+                path
+            })
+        });
+
+    Some(child.unwrap_or_default())
+}
+
+fn focus_path(component: &dyn Component, ctx: &StateContext) -> Option<Path> {
+    if component.focus_id().is_some_and(|id| ctx.is_focused(id)) {
+        return Some(vec![]);
+    }
+
+    component.children().iter().enumerate()
+        .find_map(|(i, child)| {
+            focus_path(child.as_ref(), ctx).map(|mut path| {
+                path.insert(0, i);
+                path
+            })
+        })
+}
+
+fn node<'a>(component: &'a dyn Component, path: &[usize]) -> &'a dyn Component {
+    match path.split_first() {
+        Some((i, rest)) => node(component.children()[*i].as_ref(), rest),
+        None => component,
+    }
+}
+
+fn node_mut<'a>(component: &'a mut dyn Component, path: &[usize]) -> &'a mut dyn Component {
+    match path.split_first() {
+        Some((i, rest)) => node_mut(component.children_mut()[*i].as_mut(), rest),
+        None => component,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crossterm::event::{KeyEvent, KeyModifiers};
+
+    use super::*;
+    use crate::{component::{Constraints, Rect, Size}, focus::FocusId, renderer::Renderer};
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // Logs each event it handles, and can stop it or prevent its default
+    // when it's the one handling it.
+    struct Node {
+        name: &'static str,
+        bounds: Rect,
+        children: Vec<Box<dyn Component>>,
+        focus_id: Option<FocusId>,
+        stop_in: Option<Phase>,
+        prevent_default: bool,
+        log: Log,
+    }
+
+    impl Component for Node {
+        fn render(&mut self, _renderer: &mut Renderer) {}
+
+        fn measure(&self, _constraints: Constraints) -> Size {
+            Size::new(self.bounds.width, self.bounds.height)
+        }
+
+        fn arrange(&mut self, _rect: Rect) {}
+
+        fn bounds(&self) -> Rect {
+            self.bounds
+        }
+
+        fn handle_event(&mut self, event: &ComponentEvent, ui_event: &UiEvent) {
+            let kind = match event {
+                ComponentEvent::OnClick(..) => "click",
+                ComponentEvent::Key(_) => "key",
+                ComponentEvent::Paste(_) => "paste",
+                ComponentEvent::Mouse(_) => "mouse",
+                ComponentEvent::Hover(_) => "hover",
+                ComponentEvent::Leave(_) => "leave",
+            };
+
+            self.log.borrow_mut().push(format!("{} {kind} {:?}", self.name, ui_event.phase()));
+
+            if self.stop_in == Some(ui_event.phase()) {
+                ui_event.stop_propagation();
+            }
+
+            if self.prevent_default {
+                ui_event.prevent_default();
+            }
+        }
+
+        fn children(&self) -> &[Box<dyn Component>] {
+            &self.children
+        }
+
+        fn children_mut(&mut self) -> &mut [Box<dyn Component>] {
+            &mut self.children
+        }
+
+        fn focus_id(&self) -> Option<FocusId> {
+            self.focus_id
+        }
+    }
+
+    fn node(log: &Log, name: &'static str, (x, y, width, height): (usize, usize, usize, usize)) -> Node {
+        Node {
+            name,
+            bounds: Rect { x, y, width, height },
+            children: vec![],
+            focus_id: None,
+            stop_in: None,
+            prevent_default: false,
+            log: log.clone(),
+        }
+    }
+
+    fn id(index: usize) -> FocusId {
+        FocusId { scope: 0, index }
+    }
+
+    fn tree(log: &Log) -> Node {
+        tree_stopping_in(log, None)
+    }
+
+    // A 10x10 root with a focusable panel on its left half holding a button,
+    // and a plain label on its right half. The panel stops events in the
+    // given phase.
+    fn tree_stopping_in(log: &Log, phase: Option<Phase>) -> Node {
+        let mut button = node(log, "button", (1, 1, 3, 1));
+        button.focus_id = Some(id(1));
+
+        let mut panel = node(log, "panel", (0, 0, 5, 10));
+        panel.focus_id = Some(id(0));
+        panel.stop_in = phase;
+        panel.children.push(Box::new(button));
+
+        let mut root = node(log, "root", (0, 0, 10, 10));
+        root.children = vec![Box::new(panel), Box::new(node(log, "label", (5, 0, 5, 10)))];
+        root
+    }
+
+    fn context() -> StateContext {
+        let ctx = StateContext::new();
+        ctx.focus_manager().borrow_mut().set_order(vec![id(0), id(1)]);
+        ctx
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        std::mem::take(&mut *log.borrow_mut())
+    }
+
+    fn mouse(kind: MouseEventKind, column: usize, row: usize, previous: Option<(usize, usize)>) -> ComponentEvent {
+        ComponentEvent::Mouse(MouseInput {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+            previous,
+            drag_start: None,
+        })
+    }
+
+    fn key(code: KeyCode) -> ComponentEvent {
+        ComponentEvent::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn hit_test_finds_the_deepest_component() {
+        let log = Log::default();
+        let root = tree(&log);
+
+        assert_eq!(hit_test(&root, 2, 1), Some(vec![0, 0]));
+        assert_eq!(hit_test(&root, 2, 5), Some(vec![0]));
+        assert_eq!(hit_test(&root, 7, 5), Some(vec![1]));
+        assert_eq!(hit_test(&root, 10, 0), None);
+    }
+
+    #[test]
+    fn hit_test_prefers_later_children() {
+        let log = Log::default();
+        let mut root = node(&log, "root", (0, 0, 10, 10));
+        root.children = vec![
+            Box::new(node(&log, "below", (0, 0, 10, 10))),
+            Box::new(node(&log, "above", (0, 0, 5, 5))),
+        ];
+
+        assert_eq!(hit_test(&root, 1, 1), Some(vec![1]));
+        assert_eq!(hit_test(&root, 7, 7), Some(vec![0]));
+    }
+
+    #[test]
+    fn components_without_bounds_are_never_hit() {
+        struct Bare;
+
+        impl Component for Bare {
+            fn render(&mut self, _renderer: &mut Renderer) {}
+
+            fn measure(&self, _constraints: Constraints) -> Size {
+                Size::new(1, 1)
+            }
+
+            fn arrange(&mut self, _rect: Rect) {}
+        }
+
+        let log = Log::default();
+        let mut root = node(&log, "root", (0, 0, 10, 10));
+        root.children.push(Box::new(Bare));
+
+        assert_eq!(hit_test(&root, 0, 0), Some(vec![]));
+    }
+
+    #[test]
+    fn clicks_capture_down_and_bubble_up() {
+        let log = Log::default();
+        let mut root = tree(&log);
+
+        dispatch(&mut root, &context(), &ComponentEvent::OnClick(2, 1));
+
+        assert_eq!(take(&log), [
+            "root click Capture",
+            "panel click Capture",
+            "button click Target",
+            "panel click Bubble",
+            "root click Bubble",
+        ]);
+    }
+
+    #[test]
+    fn stopping_propagation_ends_the_event_where_it_is() {
+        let log = Log::default();
+
+        let mut root = tree_stopping_in(&log, Some(Phase::Capture));
+        dispatch(&mut root, &context(), &ComponentEvent::OnClick(2, 1));
+        assert_eq!(take(&log), ["root click Capture", "panel click Capture"]);
+
+        let mut root = tree_stopping_in(&log, Some(Phase::Bubble));
+        dispatch(&mut root, &context(), &ComponentEvent::OnClick(2, 1));
+        assert_eq!(take(&log), [
+            "root click Capture",
+            "panel click Capture",
+            "button click Target",
+            "panel click Bubble",
+        ]);
+    }
+
+    #[test]
+    fn clicking_focuses_the_closest_focusable_component() {
+        let log = Log::default();
+        let mut root = tree(&log);
+        let ctx = context();
+
+        dispatch(&mut root, &ctx, &ComponentEvent::OnClick(2, 1));
+        assert!(ctx.is_focused(id(1)));
+
+        dispatch(&mut root, &ctx, &ComponentEvent::OnClick(2, 5));
+        assert!(ctx.is_focused(id(0)));
+    }
+
+    #[test]
+    fn clicking_outside_focusable_components_clears_focus() {
+        let log = Log::default();
+        let mut root = tree(&log);
+        let ctx = context();
+        ctx.focus(id(1));
+
+        dispatch(&mut root, &ctx, &ComponentEvent::OnClick(7, 5));
+        assert_eq!(ctx.focus_manager().borrow().focused(), None);
+    }
+
+    #[test]
+    fn preventing_default_keeps_focus_where_it_was() {
+        let log = Log::default();
+        let mut root = tree(&log);
+        root.prevent_default = true;
+        let ctx = context();
+        ctx.focus(id(1));
+
+        dispatch(&mut root, &ctx, &ComponentEvent::OnClick(7, 5));
+        assert!(ctx.is_focused(id(1)));
+    }
+
+    #[test]
+    fn keys_go_to_the_focused_component() {
+        let log = Log::default();
+        let mut root = tree(&log);
+        let ctx = context();
+        ctx.focus(id(0));
+
+        dispatch(&mut root, &ctx, &key(KeyCode::Char('x')));
+
+        assert_eq!(take(&log), ["root key Capture", "panel key Target", "root key Bubble"]);
+    }
+
+    #[test]
+    fn keys_reach_only_the_root_without_focus() {
+        let log = Log::default();
+        let mut root = tree(&log);
+
+        dispatch(&mut root, &context(), &key(KeyCode::Char('x')));
+
+        assert_eq!(take(&log), ["root key Target"]);
+    }
+
+    #[test]
+    fn enter_clicks_the_focused_component() {
+        let log = Log::default();
+        let mut root = tree(&log);
+        let ctx = context();
+        ctx.focus(id(1));
+
+        dispatch(&mut root, &ctx, &key(KeyCode::Enter));
+
+        assert!(take(&log).contains(&"button click Target".to_string()));
+    }
+
+    #[test]
+    fn unhandled_arrows_move_focus() {
+        let log = Log::default();
+        let mut root = tree(&log);
+        let ctx = context();
+        ctx.focus(id(0));
+
+        dispatch(&mut root, &ctx, &key(KeyCode::Down));
+        assert!(ctx.is_focused(id(1)));
+
+        dispatch(&mut root, &ctx, &key(KeyCode::Left));
+        assert!(ctx.is_focused(id(0)));
+    }
+
+    #[test]
+    fn moving_the_mouse_leaves_and_hovers_only_what_changed() {
+        let log = Log::default();
+        let mut root = tree(&log);
+        let ctx = context();
+
+        // Onto the button from outside the window
+        dispatch(&mut root, &ctx, &mouse(MouseEventKind::Moved, 2, 1, None));
+        assert_eq!(take(&log), ["root hover Target", "panel hover Target", "button hover Target"]);
+
+        // Off the button but still in the panel
+        dispatch(&mut root, &ctx, &mouse(MouseEventKind::Moved, 2, 5, Some((2, 1))));
+        assert_eq!(take(&log), ["button leave Target"]);
+
+        // Across to the label
+        dispatch(&mut root, &ctx, &mouse(MouseEventKind::Moved, 7, 5, Some((2, 5))));
+        assert_eq!(take(&log), ["panel leave Target", "label hover Target"]);
+
+        // Within the label
+        dispatch(&mut root, &ctx, &mouse(MouseEventKind::Moved, 8, 5, Some((7, 5))));
+        assert_eq!(take(&log), Vec::<String>::new());
+    }
+
+    #[test]
+    fn mouse_presses_propagate_to_the_component_under_the_mouse() {
+        let log = Log::default();
+        let mut root = tree(&log);
+
+        dispatch(&mut root, &context(), &mouse(MouseEventKind::ScrollDown, 7, 5, Some((7, 5))));
+
+        assert_eq!(take(&log), ["root mouse Capture", "label mouse Target", "root mouse Bubble"]);
+    }
+}
@@ -2,10 +2,16 @@ pub mod stack;
 pub mod text;
 pub mod center;
 pub mod button;
+pub mod provider;
+pub mod mouse;
+pub mod event;
 
 
-use crate::{prelude::{ResolvedStackWidth, StackComponent}, renderer::Renderer};
+use crossterm::event::KeyEvent;
 
+use crate::{component::{event::UiEvent, mouse::MouseInput}, focus::FocusId, line::{Line, Span}, prelude::StackWidth, renderer::Renderer, state::GetState};
+
+#[derive(Clone, Copy)]
 pub struct Rect {
     pub x: usize,
     pub y: usize,
@@ -13,6 +19,7 @@ pub struct Rect {
     pub height: usize,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Rect {
     fn default() -> Self {
         Self {
@@ -24,17 +31,135 @@ impl Default for Rect {
     }
 }
 
+impl Rect {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width &&
+        y >= self.y && y < self.y + self.height
+    }
+
+    // The part of this rect inside the other one, or an empty rect on its
+    // edge when they don't overlap.
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let x = self.x.clamp(other.x, other.x + other.width);
+        let y = self.y.clamp(other.y, other.y + other.height);
+        let right = (self.x + self.width).clamp(x, other.x + other.width);
+        let bottom = (self.y + self.height).clamp(y, other.y + other.height);
+
+        Rect { x, y, width: right - x, height: bottom - y }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Size {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Size {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height }
+    }
+}
+
+// The smallest and largest size a component may take, given by its parent
+// when measuring it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constraints {
+    pub min: Size,
+    pub max: Size,
+}
+
+impl Constraints {
+    pub fn tight(size: Size) -> Self {
+        Self { min: size, max: size }
+    }
+
+    pub fn loose(max: Size) -> Self {
+        Self { min: Size::default(), max }
+    }
+
+    pub fn unbounded() -> Self {
+        Self::loose(Size::new(usize::MAX, usize::MAX))
+    }
+
+    // Fits the size within the constraints. Where the minimum is over the
+    // maximum the maximum wins, rather than panicking like clamp would.
+    pub fn constrain(&self, size: Size) -> Size {
+        Size {
+            width: size.width.max(self.min.width).min(self.max.width),
+            height: size.height.max(self.min.height).min(self.max.height),
+        }
+    }
+
+    // The constraints left for what's inside a border or padding.
+    pub fn shrink(&self, width: usize, height: usize) -> Self {
+        Self {
+            min: Size::new(self.min.width.saturating_sub(width), self.min.height.saturating_sub(height)),
+            max: Size::new(self.max.width.saturating_sub(width), self.max.height.saturating_sub(height)),
+        }
+    }
+}
+
 pub type Sides = (usize, usize, usize, usize);
 
 pub trait Component {
-    fn propagate_event(&mut self, event: &ComponentEvent);
+    // Draws the component into the render context its parent pushed for it,
+    // which covers its bounds.
     fn render(&mut self, renderer: &mut Renderer);
-    fn resolve_stack_width(&self, stack: &StackComponent) -> ResolvedStackWidth;
+
+    // The size the component would like to be within the constraints.
+    fn measure(&self, constraints: Constraints) -> Size;
+
+    // Places the component, and anything inside it, at the rect.
+    fn arrange(&mut self, rect: Rect);
+
+    // Where the component was last arranged, used for rendering and hit-testing.
+    // Components that don't track it are empty and never hit.
+    fn bounds(&self) -> Rect {
+        Rect::default()
+    }
+
+    // How a Stack sizes the component along its stacking direction.
+    fn stack_width(&self) -> StackWidth {
+        StackWidth::Content
+    }
+
+    // Called once for each phase the event passes through this component in.
+    fn handle_event(&mut self, _event: &ComponentEvent, _ui_event: &UiEvent) {}
+
+    fn children(&self) -> &[Box<dyn Component>] {
+        &[]
+    }
+
+    fn children_mut(&mut self) -> &mut [Box<dyn Component>] {
+        &mut []
+    }
+
+    fn focus_id(&self) -> Option<FocusId> {
+        None
+    }
+
+    // Appends the focusable components of this subtree in tree order.
+    fn collect_focusable(&self, order: &mut Vec<FocusId>) {
+        if let Some(id) = self.focus_id() {
+            order.push(id);
+        }
+
+        for child in self.children() {
+            child.collect_focusable(order);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ComponentEvent {
     OnClick(usize, usize),
+    Key(KeyEvent),
+    Paste(String),
+    Mouse(MouseInput),
+    // Sent to each component the mouse moves onto or off of
+    Hover(MouseInput),
+    Leave(MouseInput),
 }
 
 pub enum ComponentValue<T> {
@@ -83,12 +208,87 @@ impl_into_component_value_fn!(
     char,
 );
 
+impl<T: Clone + 'static> IntoComponentValue<T> for GetState<T> {
+    fn into_component_value(self) -> ComponentValue<T> {
+        ComponentValue::Dynamic(Box::new(move || self.get()))
+    }
+}
+
 impl IntoComponentValue<String> for &str {
     fn into_component_value(self) -> ComponentValue<String> {
         ComponentValue::Static(self.into())
     }
 }
 
+// Text values can be plain strings, spans or lines, or closures returning any
+// of them.
+impl<F, R> IntoComponentValue<Line> for F
+where
+    F: Fn() -> R + 'static,
+    R: Into<Line>,
+{
+    fn into_component_value(self) -> ComponentValue<Line> {
+        ComponentValue::Dynamic(Box::new(move || self().into()))
+    }
+}
+
+macro_rules! impl_into_component_value_line {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl IntoComponentValue<Line> for $ty {
+                fn into_component_value(self) -> ComponentValue<Line> {
+                    ComponentValue::Static(self.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_into_component_value_line!(Line, Span, Vec<Span>, String, &str);
+
+impl IntoComponentValue<Line> for GetState<String> {
+    fn into_component_value(self) -> ComponentValue<Line> {
+        ComponentValue::Dynamic(Box::new(move || self.get().into()))
+    }
+}
+
+// Children that are built when the component asks for them rather than before
+// it runs, so it can set things up around them.
+#[derive(Default)]
+pub struct Children<'a>(Option<BuildChildren<'a>>);
+
+type BuildChildren<'a> = Box<dyn FnOnce() -> Vec<Box<dyn Component>> + 'a>;
+
+impl Children<'_> {
+    pub fn build(self) -> Vec<Box<dyn Component>> {
+        self.0.map_or_else(Vec::new, |build| build())
+    }
+}
+
+// ui! hands children over as a closure, and the type of the children prop picks
+// whether it runs right away or is kept as Children.
+pub trait IntoChildren<T> {
+    fn into_children(self) -> T;
+}
+
+impl<F> IntoChildren<Vec<Box<dyn Component>>> for F
+where
+    F: FnOnce() -> Vec<Box<dyn Component>>,
+{
+    fn into_children(self) -> Vec<Box<dyn Component>> {
+        self()
+    }
+}
+
+impl<'a, F> IntoChildren<Children<'a>> for F
+where
+    F: FnOnce() -> Vec<Box<dyn Component>> + 'a,
+{
+    fn into_children(self) -> Children<'a> {
+        Children(Some(Box::new(self)))
+    }
+}
+
 #[macro_export]
 macro_rules! cm {
     ($var:ident || $body:block) => {
@@ -112,4 +312,42 @@ macro_rules! cm {
         $(let $var = $var.clone();)+
         move || $expr
     }};
+
+    // Closures taking arguments, like event handlers
+    ($var:ident |$($arg:tt $(: $ty:ty)?),*| $expr:expr) => {{
+        let $var = $var.clone();
+        move |$($arg $(: $ty)?),*| $expr
+    }};
+
+    ([$($var:ident),+] |$($arg:tt $(: $ty:ty)?),*| $expr:expr) => {{
+        $(let $var = $var.clone();)+
+        move |$($arg $(: $ty)?),*| $expr
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constrain_keeps_sizes_within_the_limits() {
+        let constraints = Constraints { min: Size::new(2, 2), max: Size::new(5, 5) };
+
+        assert_eq!(constraints.constrain(Size::new(1, 3)), Size::new(2, 3));
+        assert_eq!(constraints.constrain(Size::new(9, 5)), Size::new(5, 5));
+    }
+
+    #[test]
+    fn constrain_prefers_the_maximum_when_the_minimum_is_over_it() {
+        let constraints = Constraints { min: Size::new(6, 1), max: Size::new(4, 4) };
+
+        assert_eq!(constraints.constrain(Size::new(2, 2)), Size::new(4, 2));
+    }
+
+    #[test]
+    fn shrinking_never_goes_below_zero() {
+        let constraints = Constraints::tight(Size::new(3, 3)).shrink(2, 5);
+
+        assert_eq!(constraints, Constraints::tight(Size::new(1, 0)));
+    }
 }
@@ -0,0 +1,84 @@
+use crossterm::event::{KeyModifiers, MouseButton, MouseEventKind};
+
+use crate::component::{event::{call, Handler, UiEvent}, ComponentEvent, Rect};
+
+// A mouse event in screen coordinates, as propagated through the tree. It
+// also carries where the mouse was before it, so components can tell when it
+// enters or leaves them, and where the current drag started.
+#[derive(Debug, Clone, Copy)]
+pub struct MouseInput {
+    pub kind: MouseEventKind,
+    pub column: usize,
+    pub row: usize,
+    pub modifiers: KeyModifiers,
+    pub previous: Option<(usize, usize)>,
+    pub drag_start: Option<(usize, usize)>,
+}
+
+impl MouseInput {
+    pub fn local(&self, bounds: &Rect) -> Mouse {
+        Mouse {
+            kind: self.kind,
+            x: self.column as isize - bounds.x as isize,
+            y: self.row as isize - bounds.y as isize,
+            modifiers: self.modifiers,
+        }
+    }
+}
+
+// A mouse event as seen by a handler, relative to the top left of the
+// component. Drags can leave the component, so the position can be negative.
+#[derive(Debug, Clone, Copy)]
+pub struct Mouse {
+    pub kind: MouseEventKind,
+    pub x: isize,
+    pub y: isize,
+    pub modifiers: KeyModifiers,
+}
+
+impl Mouse {
+    pub fn button(&self) -> Option<MouseButton> {
+        match self.kind {
+            MouseEventKind::Down(button) |
+            MouseEventKind::Up(button) |
+            MouseEventKind::Drag(button) => Some(button),
+            _ => None,
+        }
+    }
+}
+
+pub type MouseHandler = Handler<Mouse>;
+
+#[derive(Default)]
+pub(crate) struct MouseHandlers {
+    pub on_hover: Option<MouseHandler>,
+    pub on_leave: Option<MouseHandler>,
+    pub on_scroll: Option<MouseHandler>,
+    pub on_drag: Option<MouseHandler>,
+    pub on_release: Option<MouseHandler>,
+    pub on_contextmenu: Option<MouseHandler>,
+}
+
+impl MouseHandlers {
+    pub fn handle(&mut self, event: &ComponentEvent, ui_event: &UiEvent, bounds: &Rect) {
+        match event {
+            ComponentEvent::Hover(input) => call(&mut self.on_hover, input.local(bounds), ui_event),
+            ComponentEvent::Leave(input) => call(&mut self.on_leave, input.local(bounds), ui_event),
+            ComponentEvent::Mouse(input) if ui_event.bubbles() => {
+                let handler = match input.kind {
+                    MouseEventKind::ScrollUp |
+                    MouseEventKind::ScrollDown |
+                    MouseEventKind::ScrollLeft |
+                    MouseEventKind::ScrollRight => &mut self.on_scroll,
+                    MouseEventKind::Drag(_) => &mut self.on_drag,
+                    MouseEventKind::Up(_) => &mut self.on_release,
+                    MouseEventKind::Down(MouseButton::Right) => &mut self.on_contextmenu,
+                    _ => return,
+                };
+
+                call(handler, input.local(bounds), ui_event);
+            },
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{component::{Children, Component}, prelude::{Direction, Sides, StackAlign, StackComponent, StackProps, StackWidth, Stack}, state::StateContext};
+
+// Provides `value` to its children, which it builds itself once the value is
+// in place and which are the only ones to see it. `value` is an Option only so
+// the props can be defaulted; a Provider without one provides nothing.
+// Otherwise the Provider lays its children out like a Stack would.
+pub struct ProviderProps<'a, T> {
+    pub value: Option<T>,
+    pub children: Children<'a>,
+    pub direction: Direction,
+    pub width: StackWidth,
+    pub align: StackAlign,
+    pub padding: Sides,
+}
+
+impl<T> Default for ProviderProps<'_, T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            children: Children::default(),
+            direction: Direction::default(),
+            width: StackWidth::default(),
+            align: StackAlign::default(),
+            padding: Sides::default(),
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn Provider<T: 'static>(ctx: StateContext, props: ProviderProps<T>) -> StackComponent {
+    let guard = props.value.map(|value| ctx.provide(value));
+    let children: Vec<Box<dyn Component>> = props.children.build();
+    drop(guard);
+
+    Stack(ctx, StackProps { children, direction: props.direction, width: props.width, align: props.align, padding: props.padding, ..Default::default() })
+}
@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 
-use crate::{component::{Component, ComponentEvent, Rect}, prelude::Sides, renderer::Renderer, state::StateContext};
+use crossterm::{event::{KeyEvent, KeyEventKind}, style::Attribute};
+
+use crate::{component::{event::{call, Handler, UiEvent}, mouse::{MouseHandler, MouseHandlers}, Component, ComponentEvent, Constraints, Rect, Size}, focus::FocusId, prelude::Sides, renderer::Renderer, state::StateContext, style::Style};
 
 #[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
@@ -16,7 +18,7 @@ pub enum StackWidth {
 }
 
 #[derive(Copy, Clone)]
-pub enum ResolvedStackWidth {
+enum ResolvedStackWidth {
     Flex(usize),
     Exact(usize),
 }
@@ -28,6 +30,16 @@ pub enum StackAlign {
     End,
 }
 
+// Where children go across the stacking direction. Stretched children fill
+// the stack, the rest are as big as they measure.
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+pub enum CrossAlign {
+    #[default] Stretch,
+    Start,
+    Center,
+    End,
+}
+
 enum WidthSegment {
     Child(usize, usize),
     Filler(usize),
@@ -38,15 +50,32 @@ pub struct StackProps {
     pub border: bool,
     pub direction: Direction,
     pub children: Vec<Box<dyn Component>>,
-    pub on_click: Option<Box<dyn FnMut()>>,
+    pub on_click: Option<Handler>,
+    pub on_click_capture: Option<Handler>,
+    pub on_key: Option<Handler<KeyEvent>>,
+    pub on_keydown: Option<Handler<KeyEvent>>,
+    pub on_key_capture: Option<Handler<KeyEvent>>,
+    pub on_paste: Option<Handler<String>>,
+    pub on_hover: Option<MouseHandler>,
+    pub on_leave: Option<MouseHandler>,
+    pub on_scroll: Option<MouseHandler>,
+    pub on_drag: Option<MouseHandler>,
+    pub on_release: Option<MouseHandler>,
+    pub on_contextmenu: Option<MouseHandler>,
     pub width: StackWidth,
     pub align: StackAlign,
+    pub cross_align: CrossAlign,
     pub padding: Sides,
+    pub focusable: bool,
+    pub style: Style,
 }
 
 pub struct StackComponent {
     bounds: Rect,
     props: StackProps,
+    ctx: StateContext,
+    focus_id: Option<FocusId>,
+    mouse_handlers: MouseHandlers,
 }
 
 impl StackComponent {
@@ -58,15 +87,52 @@ impl StackComponent {
         self.props.direction
     }
 
-    fn calc_render_widths(&self, renderer: &mut Renderer) -> Vec<WidthSegment> {
+    pub fn is_focused(&self) -> bool {
+        self.focus_id.is_some_and(|id| self.ctx.is_focused(id))
+    }
+
+    // Space taken up by the border and padding, horizontally then vertically.
+    fn frame_size(&self) -> (usize, usize) {
+        let border = if self.props.border { 2 } else { 0 };
+        let padding = self.props.padding;
+
+        (border + padding.1 + padding.3, border + padding.0 + padding.2)
+    }
+
+    // Splits a size into its length along the stacking direction and across it.
+    fn split_axes(&self, size: Size) -> (usize, usize) {
+        match self.props.direction {
+            Direction::Row => (size.height, size.width),
+            Direction::Column => (size.width, size.height),
+        }
+    }
+
+    fn join_axes(&self, main: usize, cross: usize) -> Size {
+        match self.props.direction {
+            Direction::Row => Size::new(cross, main),
+            Direction::Column => Size::new(main, cross),
+        }
+    }
+
+    // What a child is measured with when it can be up to `main` long. A
+    // stretched child is told it will be exactly `cross` across.
+    fn child_constraints(&self, main: usize, cross: usize) -> Constraints {
+        let max = self.join_axes(main, cross);
+
+        match self.props.cross_align {
+            CrossAlign::Stretch => Constraints { min: self.join_axes(0, cross), max },
+            _ => Constraints::loose(max),
+        }
+    }
+
+    #[allow(clippy::redundant_pattern_matching)]
+    fn calc_render_widths(&self, total_potential_width: usize, resolved: &[ResolvedStackWidth]) -> Vec<WidthSegment> {
         let mut flex_total: usize = 0;
         let mut flex_count: usize = 0;
 
         let mut exact_total: usize = 0;
 
-        for child in &self.props.children {
-            let width = child.resolve_stack_width(self);
-
+        for width in resolved {
             match width {
                 ResolvedStackWidth::Flex(val) => {
                     flex_total += val;
@@ -78,20 +144,13 @@ impl StackComponent {
             }
         }
 
-        let render_context = renderer.current_render_context();
-
-        let total_potential_width = match self.props.direction {
-            Direction::Row => render_context.height,
-            Direction::Column => render_context.width,
-        };
-
         let total_flex_width = total_potential_width - exact_total.min(total_potential_width);
 
         let mut widths = Vec::<WidthSegment>::new();
         let mut flex_remainders = Vec::<(f32, usize)>::new();
         let mut total_width: usize = 0;
 
-        let mut children: Vec<Option<&Box<dyn Component>>> = vec![];
+        let mut children: Vec<Option<ResolvedStackWidth>> = vec![];
 
         let has_flex_children = flex_count > 0;
 
@@ -106,8 +165,8 @@ impl StackComponent {
             };
         }
 
-        self.props.children.iter()
-            .for_each(|child| children.push(Some(child)));
+        resolved.iter()
+            .for_each(|width| children.push(Some(*width)));
 
         if !has_flex_children {
             match self.props.align {
@@ -123,11 +182,7 @@ impl StackComponent {
         let mut i = 0;
 
         for child in &children {
-            let width = if let Some(child) = child {
-                child.resolve_stack_width(self)
-            } else {
-                ResolvedStackWidth::Flex(1)
-            };
+            let width = child.unwrap_or(ResolvedStackWidth::Flex(1));
 
             let width_amount = match width {
                 ResolvedStackWidth::Flex(val) => {
@@ -175,27 +230,138 @@ impl StackComponent {
 
         widths
     }
+}
 
-    fn render_children(&mut self, renderer: &mut Renderer) {
-        let widths = self.calc_render_widths(renderer);
-
+impl Component for StackComponent {
+    fn render(&mut self, renderer: &mut Renderer) {
         let render_context = renderer.current_render_context();
+
         let width = render_context.width;
         let height = render_context.height;
 
+        if width == 0 && height == 0 {
+            return;
+        }
+
+        renderer.push_style(self.props.style);
+
+        // The background covers the whole stack, not just what's drawn in it
+        if self.props.style.bg.is_some() {
+            renderer.fill(0, 0, width, height);
+        }
+
+        let (inner_x, inner_y, inner_width, inner_height) = if self.props.border {
+            renderer.draw_box(0, 0, width, height);
+            (1, 1, width - 2.min(width), height - 2.min(height))
+        } else {
+            (0, 0, width, height)
+        };
+
+        for child in &mut self.props.children {
+            let bounds = child.bounds();
+
+            renderer.push_absolute_render_context(bounds.x, bounds.y, bounds.width, bounds.height);
+            child.render(renderer);
+            renderer.pop_render_context();
+        }
+
+        renderer.pop_style();
+
+        if self.is_focused() {
+            renderer.apply_attribute(inner_x, inner_y, inner_width, inner_height, Attribute::Reverse);
+        }
+    }
+
+    // Children are measured without the border and padding, and laid end to
+    // end along the stacking direction.
+    fn measure(&self, constraints: Constraints) -> Size {
+        let (frame_width, frame_height) = self.frame_size();
+        let inner = Constraints::loose(constraints.shrink(frame_width, frame_height).max);
+
+        let mut main = 0;
+        let mut cross = 0;
+
+        for child in &self.props.children {
+            let (child_main, child_cross) = self.split_axes(child.measure(inner));
+
+            main += match child.stack_width() {
+                StackWidth::Exact(val) => val,
+                _ => child_main,
+            };
+
+            cross = cross.max(child_cross);
+        }
+
+        let size = self.join_axes(main, cross);
+
+        constraints.constrain(Size::new(size.width + frame_width, size.height + frame_height))
+    }
+
+    // Children share the length along the stacking direction by their
+    // stack_width, and are placed across it by cross_align.
+    fn arrange(&mut self, rect: Rect) {
+        self.bounds = rect;
+
+        let (x, y, width, height) = if self.props.border {
+            (rect.x + 1, rect.y + 1, rect.width - 2.min(rect.width), rect.height - 2.min(rect.height))
+        } else {
+            (rect.x, rect.y, rect.width, rect.height)
+        };
+
+        let padding = self.props.padding;
+
+        let inner = Rect {
+            x: x + padding.3,
+            y: y + padding.0,
+            width: width - (padding.3 + padding.1).min(width),
+            height: height - (padding.0 + padding.2).min(height),
+        };
+
+        let (total_potential_width, total_cross) = self.split_axes(Size::new(inner.width, inner.height));
+
+        let resolved: Vec<ResolvedStackWidth> = self.props.children.iter()
+            .map(|child| match child.stack_width() {
+                StackWidth::Content => {
+                    let constraints = self.child_constraints(total_potential_width, total_cross);
+                    let (main, _) = self.split_axes(child.measure(constraints));
+                    ResolvedStackWidth::Exact(main)
+                },
+                StackWidth::Flex(val) => ResolvedStackWidth::Flex(val),
+                StackWidth::Exact(val) => ResolvedStackWidth::Exact(val),
+            })
+            .collect();
+
+        let widths = self.calc_render_widths(total_potential_width, &resolved);
+
         let mut offset: usize = 0;
 
         for width_segment in widths {
             let amount = match width_segment {
                 WidthSegment::Child(amount, i) => {
-                    let (new_x, new_y, new_width, new_height) = match self.props.direction {
-                        Direction::Row => (0, offset, width, amount),
-                        Direction::Column => (offset, 0, amount, height),
+                    let child = &self.props.children[i];
+
+                    let cross = match self.props.cross_align {
+                        CrossAlign::Stretch => total_cross,
+                        _ => {
+                            let (_, cross) = self.split_axes(child.measure(self.child_constraints(amount, total_cross)));
+                            cross.min(total_cross)
+                        },
+                    };
+
+                    let free = total_cross - cross;
+
+                    let cross_offset = match self.props.cross_align {
+                        CrossAlign::Stretch | CrossAlign::Start => 0,
+                        CrossAlign::Center => free / 2,
+                        CrossAlign::End => free,
+                    };
+
+                    let child_rect = match self.props.direction {
+                        Direction::Row => Rect { x: inner.x + cross_offset, y: inner.y + offset, width: cross, height: amount },
+                        Direction::Column => Rect { x: inner.x + offset, y: inner.y + cross_offset, width: amount, height: cross },
                     };
 
-                    renderer.push_relative_render_context(new_x, new_y, new_width, new_height);
-                    self.props.children[i].render(renderer);
-                    renderer.pop_render_context();
+                    self.props.children[i].arrange(child_rect.intersect(&inner));
 
                     amount
                 },
@@ -205,111 +371,200 @@ impl StackComponent {
             offset += amount;
         }
     }
-}
 
-impl Component for StackComponent {
-    fn render(&mut self, renderer: &mut Renderer) {
-        let render_context = renderer.current_render_context();
+    fn handle_event(&mut self, event: &ComponentEvent, ui_event: &UiEvent) {
+        match event {
+            ComponentEvent::OnClick(..) => {
+                if ui_event.captures() {
+                    call(&mut self.props.on_click_capture, (), ui_event);
+                }
 
-        self.bounds = Rect {
-            x: render_context.x,
-            y: render_context.y,
-            width: render_context.width,
-            height: render_context.height,
-        };
+                if ui_event.bubbles() {
+                    call(&mut self.props.on_click, (), ui_event);
+                }
+            },
+            ComponentEvent::Key(key) => {
+                if ui_event.captures() {
+                    call(&mut self.props.on_key_capture, *key, ui_event);
+                }
 
-        let width = render_context.width;
-        let height = render_context.height;
+                if ui_event.bubbles() {
+                    call(&mut self.props.on_key, *key, ui_event);
 
-        if width == 0 && height == 0 {
-            return;
+                    if key.kind != KeyEventKind::Release {
+                        call(&mut self.props.on_keydown, *key, ui_event);
+                    }
+                }
+            },
+            ComponentEvent::Paste(text) => {
+                if ui_event.bubbles() {
+                    call(&mut self.props.on_paste, text.clone(), ui_event);
+                }
+            },
+            _ => self.mouse_handlers.handle(event, ui_event, &self.bounds),
         }
+    }
 
-        let (mut x, mut y, mut width, mut height) = if self.props.border {
-            renderer.draw_box(0, 0, width, height);
-            (1, 1, width - 2.min(width), height - 2.min(height))
-        } else {
-            (0, 0, width, height)
-        };
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
 
-        x += self.props.padding.3;
-        y += self.props.padding.0;
-        width -= (self.props.padding.3 + self.props.padding.1).min(width);
-        height -= (self.props.padding.0 + self.props.padding.2).min(width);
+    fn stack_width(&self) -> StackWidth {
+        self.props.width
+    }
 
-        renderer.push_relative_render_context(x, y, width, height);
+    fn children(&self) -> &[Box<dyn Component>] {
+        &self.props.children
+    }
 
-        self.render_children(renderer);
+    fn children_mut(&mut self) -> &mut [Box<dyn Component>] {
+        &mut self.props.children
+    }
 
-        renderer.pop_render_context();
+    fn focus_id(&self) -> Option<FocusId> {
+        self.focus_id
     }
+}
 
-    fn propagate_event(&mut self, event: &ComponentEvent) {
-        match event {
-            ComponentEvent::OnClick(x, y) => {
-                if 
-                    *x < self.bounds.x || *x >= self.bounds.x + self.bounds.width ||
-                    *y < self.bounds.y || *y >= self.bounds.y + self.bounds.height 
-                {
-                    return;
-                }
+#[allow(non_snake_case)]
+pub fn Stack(ctx: StateContext, mut props: StackProps) -> StackComponent {
+    let _scope_guard = ctx.keyed_scope("Stack");
+    let focus_id = props.focusable.then(|| ctx.focus_id());
+
+    let mouse_handlers = MouseHandlers {
+        on_hover: props.on_hover.take(),
+        on_leave: props.on_leave.take(),
+        on_scroll: props.on_scroll.take(),
+        on_drag: props.on_drag.take(),
+        on_release: props.on_release.take(),
+        on_contextmenu: props.on_contextmenu.take(),
+    };
 
-                if let Some(mut on_click) = self.props.on_click.take() {
-                    on_click();
-                    self.props.on_click = Some(on_click);
-                }
-            }
+    StackComponent {
+        bounds: Rect::default(),
+        props,
+        ctx,
+        focus_id,
+        mouse_handlers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    // Wants a fixed size, and remembers what it was last measured with.
+    struct Probe {
+        size: Size,
+        bounds: Rect,
+        measured: Rc<Cell<Option<Constraints>>>,
+    }
+
+    impl Component for Probe {
+        fn render(&mut self, _renderer: &mut Renderer) {}
+
+        fn measure(&self, constraints: Constraints) -> Size {
+            self.measured.set(Some(constraints));
+            constraints.constrain(self.size)
         }
 
-        for child in &mut self.props.children {
-            child.propagate_event(event);
+        fn arrange(&mut self, rect: Rect) {
+            self.bounds = rect;
+        }
+
+        fn bounds(&self) -> Rect {
+            self.bounds
         }
     }
 
-    fn resolve_stack_width(&self, stack: &StackComponent) -> ResolvedStackWidth {
-        match self.props.width {
-            StackWidth::Content => {
-                let mut total_width = if self.props.border {
-                    2
-                } else {
-                    0
-                };
+    fn probe(width: usize, height: usize) -> (Box<dyn Component>, Rc<Cell<Option<Constraints>>>) {
+        let measured = Rc::new(Cell::new(None));
 
-                total_width += match stack.get_direction() {
-                    Direction::Row => self.props.padding.0 + self.props.padding.2,
-                    Direction::Column => self.props.padding.1 + self.props.padding.3,
-                };
+        let probe = Probe {
+            size: Size::new(width, height),
+            bounds: Rect::default(),
+            measured: measured.clone(),
+        };
 
-                if self.get_direction() != stack.get_direction() {
-                    let mut max = 0;
+        (Box::new(probe), measured)
+    }
 
-                    for child in &self.props.children {
-                        if let ResolvedStackWidth::Exact(val) = child.resolve_stack_width(stack) {
-                            max = max.max(val);
-                        }
-                    }
+    fn arranged(direction: Direction, cross_align: CrossAlign, children: Vec<Box<dyn Component>>) -> Vec<Rect> {
+        let props = StackProps { direction, cross_align, children, ..Default::default() };
+        let mut stack = Stack(StateContext::new(), props);
 
-                    total_width += max;
-                } else {
-                    for child in &self.props.children {
-                        if let ResolvedStackWidth::Exact(val) = child.resolve_stack_width(stack) {
-                            total_width += val;
-                        }
-                    }
-                }
+        stack.arrange(Rect { x: 1, y: 1, width: 10, height: 6 });
+        stack.children().iter().map(|child| child.bounds()).collect()
+    }
 
-                ResolvedStackWidth::Exact(total_width)
-            },
-            StackWidth::Flex(val) => ResolvedStackWidth::Flex(val),
-            StackWidth::Exact(val) => ResolvedStackWidth::Exact(val),
-        }
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> (usize, usize, usize, usize) {
+        (x, y, width, height)
     }
-}
 
-#[allow(non_snake_case)]
-pub fn Stack(_ctx: StateContext, props: StackProps) -> StackComponent {
-    StackComponent {
-        bounds: Rect::default(),
-        props
+    fn parts(rects: Vec<Rect>) -> Vec<(usize, usize, usize, usize)> {
+        rects.into_iter().map(|rect| (rect.x, rect.y, rect.width, rect.height)).collect()
+    }
+
+    #[test]
+    fn stretched_children_fill_the_cross_axis() {
+        let (a, _) = probe(3, 2);
+        let (b, _) = probe(5, 1);
+
+        assert_eq!(parts(arranged(Direction::Row, CrossAlign::Stretch, vec![a, b])), [
+            rect(1, 1, 10, 2),
+            rect(1, 3, 10, 1),
+        ]);
+    }
+
+    #[test]
+    fn stretched_children_are_measured_tight_across() {
+        let (child, measured) = probe(3, 2);
+        arranged(Direction::Row, CrossAlign::Stretch, vec![child]);
+
+        let constraints = measured.get().unwrap();
+        assert_eq!(constraints.min.width, 10);
+        assert_eq!(constraints.max.width, 10);
+    }
+
+    #[test]
+    fn aligned_children_keep_their_measured_cross_size() {
+        let children = || vec![probe(3, 2).0, probe(4, 1).0];
+
+        assert_eq!(parts(arranged(Direction::Row, CrossAlign::Start, children())), [
+            rect(1, 1, 3, 2),
+            rect(1, 3, 4, 1),
+        ]);
+
+        assert_eq!(parts(arranged(Direction::Row, CrossAlign::Center, children())), [
+            rect(4, 1, 3, 2),
+            rect(4, 3, 4, 1),
+        ]);
+
+        assert_eq!(parts(arranged(Direction::Row, CrossAlign::End, children())), [
+            rect(8, 1, 3, 2),
+            rect(7, 3, 4, 1),
+        ]);
+    }
+
+    #[test]
+    fn cross_alignment_follows_the_direction() {
+        let (child, _) = probe(3, 2);
+
+        assert_eq!(parts(arranged(Direction::Column, CrossAlign::End, vec![child])), [
+            rect(1, 5, 3, 2),
+        ]);
+    }
+
+    #[test]
+    fn aligned_children_are_cut_to_the_stack() {
+        let (child, measured) = probe(20, 1);
+
+        assert_eq!(parts(arranged(Direction::Row, CrossAlign::Center, vec![child])), [
+            rect(1, 1, 10, 1),
+        ]);
+
+        assert_eq!(measured.get().unwrap().min, Size::default());
     }
 }
@@ -1,94 +1,479 @@
-use crossterm::style::Stylize;
+use std::{cell::RefCell, mem};
 
-use crate::{component::{Component, ComponentEvent, ComponentValue, Rect}, prelude::{Direction, ResolvedStackWidth, Sides, StackComponent, StackWidth}, renderer::Renderer, state::StateContext};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{component::{event::{call, Handler, UiEvent}, mouse::{MouseHandler, MouseHandlers}, Component, ComponentEvent, ComponentValue, Constraints, Rect, Size}, line::Line, prelude::{Sides, StackWidth}, renderer::Renderer, screen_buffer::{grapheme_width, BufferCell}, state::StateContext, style::Style};
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    #[default] None,
+    Char,
+    Word,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default] Left,
+    Center,
+    Right,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    #[default] Clip,
+    Ellipsis,
+}
+
+const ELLIPSIS: &str = "…";
+
+#[derive(Clone, Copy)]
+struct Glyph<'a> {
+    grapheme: &'a str,
+    style: Style,
+    width: usize,
+}
+
+type Row<'a> = Vec<Glyph<'a>>;
+
+fn row_width(row: &[Glyph]) -> usize {
+    row.iter().map(|glyph| glyph.width).sum()
+}
+
+fn is_space(glyph: &Glyph) -> bool {
+    glyph.grapheme.trim().is_empty()
+}
+
+// Splits the line into rows at each newline.
+fn split_rows(line: &Line) -> Vec<Row<'_>> {
+    let mut rows = vec![vec![]];
+
+    for span in &line.spans {
+        for grapheme in span.content.graphemes(true) {
+            if grapheme == "\n" || grapheme == "\r\n" {
+                rows.push(vec![]);
+                continue;
+            }
+
+            let width = grapheme_width(grapheme);
+
+            if width > 0
+                && let Some(row) = rows.last_mut()
+            {
+                row.push(Glyph { grapheme, style: span.style, width });
+            }
+        }
+    }
+
+    rows
+}
+
+fn wrap_chars(row: Row<'_>, width: usize) -> Vec<Row<'_>> {
+    let mut rows = vec![];
+    let mut current = vec![];
+    let mut current_width = 0;
+
+    for glyph in row {
+        if !current.is_empty() && current_width + glyph.width > width {
+            rows.push(mem::take(&mut current));
+            current_width = 0;
+        }
+
+        current_width += glyph.width;
+        current.push(glyph);
+    }
+
+    rows.push(current);
+    rows
+}
+
+// Breaks between words where it can, dropping the spaces it breaks at. Words
+// too long for a row of their own are broken between characters.
+fn wrap_words(row: Row<'_>, width: usize) -> Vec<Row<'_>> {
+    let mut rows = vec![];
+    let mut current: Row = vec![];
+    let mut current_width = 0;
+
+    for word in row.chunk_by(|a, b| is_space(a) == is_space(b)) {
+        let word_width = row_width(word);
+
+        if current_width + word_width <= width {
+            current.extend_from_slice(word);
+            current_width += word_width;
+            continue;
+        }
+
+        if !current.is_empty() {
+            while current.last().is_some_and(is_space) {
+                current.pop();
+            }
+
+            rows.push(mem::take(&mut current));
+            current_width = 0;
+        }
+
+        if word.first().is_some_and(is_space) {
+            continue;
+        }
+
+        let mut pieces = wrap_chars(word.to_vec(), width);
+        current = pieces.pop().unwrap_or_default();
+        current_width = row_width(&current);
+        rows.extend(pieces);
+    }
+
+    // Spaces dropped at the last break leave nothing for another row
+    if !current.is_empty() || rows.is_empty() {
+        rows.push(current);
+    }
+
+    rows
+}
+
+// Cuts the row down to fit with an ellipsis on the end.
+fn ellipsize(mut row: Row<'_>, width: usize) -> Row<'_> {
+    let Some(first) = row.first().copied() else {
+        return row;
+    };
+
+    while !row.is_empty() && row_width(&row) + 1 > width {
+        row.pop();
+    }
+
+    while row.last().is_some_and(is_space) {
+        row.pop();
+    }
+
+    let style = row.last().unwrap_or(&first).style;
+    row.push(Glyph { grapheme: ELLIPSIS, style, width: 1 });
+    row
+}
+
+fn layout(line: &Line, wrap: Wrap, width: usize) -> Vec<Row<'_>> {
+    let rows = split_rows(line);
+
+    if width == 0 {
+        return rows;
+    }
+
+    match wrap {
+        Wrap::None => rows,
+        Wrap::Char => rows.into_iter().flat_map(|row| wrap_chars(row, width)).collect(),
+        Wrap::Word => rows.into_iter().flat_map(|row| wrap_words(row, width)).collect(),
+    }
+}
 
 pub struct TextComponent {
     bounds: Rect,
-    value: ComponentValue<String>,
-    on_click: Option<Box<dyn FnMut()>>,
+    value: ComponentValue<Line>,
+    // A dynamic value as evaluated for the current layout pass
+    evaluated: RefCell<Option<Line>>,
+    on_click: Option<Handler>,
+    on_click_capture: Option<Handler>,
+    mouse_handlers: MouseHandlers,
     width: StackWidth,
     padding: Sides,
+    style: Style,
+    wrap: Wrap,
+    align: TextAlign,
+    overflow: Overflow,
 }
 
-impl Component for TextComponent {
-    fn render(&mut self, renderer: &mut Renderer) {
+impl TextComponent {
+    // Calls `f` with the value. A dynamic one is evaluated the first time it's
+    // needed in a layout pass, and kept until render is done with it, so the
+    // text measured is the text drawn.
+    fn with_value<R>(&self, f: impl FnOnce(&Line) -> R) -> R {
+        match &self.value {
+            ComponentValue::Static(value) => f(value),
+            ComponentValue::Dynamic(value_fn) => f(self.evaluated.borrow_mut().get_or_insert_with(value_fn)),
+        }
+    }
+
+    fn draw(&self, value: &Line, renderer: &mut Renderer) {
         let render_context = renderer.current_render_context();
 
-        self.bounds = Rect {
-            x: render_context.x,
-            y: render_context.y,
-            width: render_context.width,
-            height: render_context.height,
-        };
+        let width = render_context.width - (self.padding.1 + self.padding.3).min(render_context.width);
+        let height = render_context.height - (self.padding.0 + self.padding.2).min(render_context.height);
+
+        let mut rows = layout(value, self.wrap, width);
 
-        let value = match &self.value {
-            ComponentValue::Static(value) => value,
-            ComponentValue::Dynamic(value_fn) => &value_fn(),
-        };
+        if self.overflow == Overflow::Ellipsis && width > 0 {
+            let hidden = rows.len() > height;
+            rows.truncate(height);
 
-        for (i, c) in value.chars().enumerate() {
-            renderer.set(i + self.padding.3, self.padding.0, c.stylize());
+            for (i, row) in rows.iter_mut().enumerate() {
+                if row_width(row) > width || (hidden && i + 1 == height) {
+                    *row = ellipsize(mem::take(row), width);
+                }
+            }
+        }
+
+        let text_style = self.style.inherit(&renderer.current_style());
+
+        for (y, row) in rows.iter().enumerate() {
+            let free = width - row_width(row).min(width);
+
+            let mut x = self.padding.3 + match self.align {
+                TextAlign::Left => 0,
+                TextAlign::Center => free / 2,
+                TextAlign::Right => free,
+            };
+
+            for glyph in row {
+                let style = glyph.style.inherit(&text_style).content_style();
+                renderer.set(x, y + self.padding.0, BufferCell::grapheme(style, glyph.grapheme));
+                x += glyph.width;
+            }
         }
     }
+}
 
-    fn propagate_event(&mut self, event: &ComponentEvent) {
+impl Component for TextComponent {
+    fn render(&mut self, renderer: &mut Renderer) {
+        self.with_value(|value| self.draw(value, renderer));
+
+        // The next pass evaluates the value again
+        self.evaluated.get_mut().take();
+    }
+
+    fn handle_event(&mut self, event: &ComponentEvent, ui_event: &UiEvent) {
         match event {
-            ComponentEvent::OnClick(x, y) => {
-                if 
-                    *x < self.bounds.x || *x >= self.bounds.x + self.bounds.width ||
-                    *y < self.bounds.y || *y >= self.bounds.y + self.bounds.height 
-                {
-                    return;
+            ComponentEvent::OnClick(..) => {
+                if ui_event.captures() {
+                    call(&mut self.on_click_capture, (), ui_event);
                 }
 
-                if let Some(mut on_click) = self.on_click.take() {
-                    on_click();
-                    self.on_click = Some(on_click);
+                if ui_event.bubbles() {
+                    call(&mut self.on_click, (), ui_event);
                 }
-            }
+            },
+            ComponentEvent::Key(_) => {},
+            _ => self.mouse_handlers.handle(event, ui_event, &self.bounds),
         }
     }
 
-    fn resolve_stack_width(&self, stack: &StackComponent) -> ResolvedStackWidth {
-        match self.width {
-            StackWidth::Content => {
-                let direction = stack.get_direction();
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
 
-                let val = match direction {
-                    Direction::Row => 1 + self.padding.0 + self.padding.2,
-                    Direction::Column => {
-                        let value = match &self.value {
-                            ComponentValue::Static(value) => value,
-                            ComponentValue::Dynamic(value_fn) => &value_fn(),
-                        };
+    // Wrapped to the width it's allowed, as wide as its widest row and as
+    // tall as the number of rows.
+    fn measure(&self, constraints: Constraints) -> Size {
+        let padding_width = self.padding.1 + self.padding.3;
+        let padding_height = self.padding.0 + self.padding.2;
 
-                        value.len() + self.padding.1 + self.padding.3
-                    }
-                };
+        let (widest, height) = self.with_value(|value| {
+            let rows = layout(value, self.wrap, constraints.max.width.saturating_sub(padding_width));
 
-                ResolvedStackWidth::Exact(val)
-            },
-            StackWidth::Flex(val) => ResolvedStackWidth::Flex(val),
-            StackWidth::Exact(val) => ResolvedStackWidth::Exact(val),
-        }
+            let widest = rows.iter()
+                .map(|row| row_width(row))
+                .max()
+                .unwrap_or(0);
+
+            (widest, rows.len())
+        });
+
+        constraints.constrain(Size::new(widest + padding_width, height + padding_height))
+    }
+
+    fn arrange(&mut self, rect: Rect) {
+        self.bounds = rect;
+    }
+
+    fn stack_width(&self) -> StackWidth {
+        self.width
     }
 }
 
 #[derive(Default)]
 pub struct TextProps {
-    pub value: ComponentValue<String>,
-    pub on_click: Option<Box<dyn FnMut()>>,
+    pub value: ComponentValue<Line>,
+    pub on_click: Option<Handler>,
+    pub on_click_capture: Option<Handler>,
+    pub on_hover: Option<MouseHandler>,
+    pub on_leave: Option<MouseHandler>,
+    pub on_scroll: Option<MouseHandler>,
+    pub on_drag: Option<MouseHandler>,
+    pub on_release: Option<MouseHandler>,
+    pub on_contextmenu: Option<MouseHandler>,
     pub width: StackWidth,
     pub padding: Sides,
+    pub style: Style,
+    pub wrap: Wrap,
+    pub align: TextAlign,
+    pub overflow: Overflow,
 }
 
 #[allow(non_snake_case)]
-pub fn Text(_ctx: StateContext, props: TextProps) -> TextComponent {
+pub fn Text(ctx: StateContext, props: TextProps) -> TextComponent {
+    let _scope_guard = ctx.keyed_scope("Text");
+
     TextComponent {
         bounds: Rect::default(),
         value: props.value,
+        evaluated: RefCell::new(None),
         on_click: props.on_click,
+        on_click_capture: props.on_click_capture,
+        mouse_handlers: MouseHandlers {
+            on_hover: props.on_hover,
+            on_leave: props.on_leave,
+            on_scroll: props.on_scroll,
+            on_drag: props.on_drag,
+            on_release: props.on_release,
+            on_contextmenu: props.on_contextmenu,
+        },
         width: props.width,
         padding: props.padding,
+        style: props.style,
+        wrap: props.wrap,
+        align: props.align,
+        overflow: props.overflow,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::{component::stack::{Stack, StackProps}, line::Span};
+
+    fn to_string(row: &[Glyph]) -> String {
+        row.iter().map(|glyph| glyph.grapheme).collect()
+    }
+
+    fn wrapped(text: &str, wrap: Wrap, width: usize) -> Vec<String> {
+        let line = Line::from(text);
+
+        layout(&line, wrap, width).iter()
+            .map(|row| to_string(row))
+            .collect()
+    }
+
+    fn ellipsized(text: &str, width: usize) -> String {
+        let line = Line::from(text);
+        let row = split_rows(&line).remove(0);
+
+        to_string(&ellipsize(row, width))
+    }
+
+    #[test]
+    fn rows_split_at_newlines() {
+        assert_eq!(wrapped("a\nb\r\nc", Wrap::None, 10), ["a", "b", "c"]);
+        assert_eq!(wrapped("\n", Wrap::None, 10), ["", ""]);
+        assert_eq!(wrapped("", Wrap::None, 10), [""]);
+    }
+
+    #[test]
+    fn without_wrapping_rows_keep_their_length() {
+        assert_eq!(wrapped("abcdef", Wrap::None, 4), ["abcdef"]);
+    }
+
+    #[test]
+    fn char_wrapping_fills_each_row() {
+        assert_eq!(wrapped("abcdef", Wrap::Char, 4), ["abcd", "ef"]);
+        assert_eq!(wrapped("ab cd", Wrap::Char, 3), ["ab ", "cd"]);
+    }
+
+    #[test]
+    fn char_wrapping_keeps_wide_graphemes_whole() {
+        assert_eq!(wrapped("日本語", Wrap::Char, 3), ["日", "本", "語"]);
+        assert_eq!(wrapped("日本語", Wrap::Char, 5), ["日本", "語"]);
+    }
+
+    #[test]
+    fn word_wrapping_breaks_between_words() {
+        assert_eq!(wrapped("hello big world", Wrap::Word, 9), ["hello big", "world"]);
+        assert_eq!(wrapped("hello big world", Wrap::Word, 15), ["hello big world"]);
+    }
+
+    #[test]
+    fn word_wrapping_drops_the_spaces_it_breaks_at() {
+        assert_eq!(wrapped("aaa   bbb", Wrap::Word, 4), ["aaa", "bbb"]);
+        assert_eq!(wrapped("aaa bbb ", Wrap::Word, 3), ["aaa", "bbb"]);
+    }
+
+    #[test]
+    fn word_wrapping_keeps_leading_spaces() {
+        assert_eq!(wrapped("  ab cd", Wrap::Word, 5), ["  ab", "cd"]);
+    }
+
+    #[test]
+    fn word_wrapping_breaks_long_words_between_characters() {
+        assert_eq!(wrapped("abcdefghij xy", Wrap::Word, 4), ["abcd", "efgh", "ij", "xy"]);
+        assert_eq!(wrapped("a abcdefg", Wrap::Word, 4), ["a", "abcd", "efg"]);
+    }
+
+    #[test]
+    fn wrapping_applies_to_each_row() {
+        assert_eq!(wrapped("ab cd\nef", Wrap::Word, 3), ["ab", "cd", "ef"]);
+    }
+
+    #[test]
+    fn a_zero_width_doesnt_wrap() {
+        assert_eq!(wrapped("ab cd", Wrap::Word, 0), ["ab cd"]);
+        assert_eq!(wrapped("ab cd", Wrap::Char, 0), ["ab cd"]);
+    }
+
+    #[test]
+    fn ellipsizing_ends_the_row_with_an_ellipsis() {
+        assert_eq!(ellipsized("hello world", 6), "hello…");
+        assert_eq!(ellipsized("hello world", 9), "hello wo…");
+        assert_eq!(ellipsized("hello", 1), "…");
+    }
+
+    #[test]
+    fn ellipsizing_drops_spaces_before_the_ellipsis() {
+        assert_eq!(ellipsized("hello world", 7), "hello…");
+    }
+
+    #[test]
+    fn ellipsizing_keeps_wide_graphemes_whole() {
+        assert_eq!(ellipsized("日本語", 4), "日…");
+        assert_eq!(ellipsized("日本語", 2), "…");
+    }
+
+    #[test]
+    fn ellipsizing_an_empty_row_adds_nothing() {
+        assert_eq!(ellipsized("", 4), "");
+    }
+
+    #[test]
+    fn the_ellipsis_takes_the_style_of_what_it_follows() {
+        let bold = Style::new().bold();
+        let line = Line::new(vec![Span::styled("ab", bold), Span::new("cdef")]);
+        let row = ellipsize(split_rows(&line).remove(0), 3);
+
+        assert_eq!(to_string(&row), "ab…");
+        assert_eq!(row.last().unwrap().style, bold);
+    }
+
+    #[test]
+    fn dynamic_values_are_evaluated_once_per_layout_pass() {
+        let calls = Rc::new(Cell::new(0));
+
+        let value = ComponentValue::Dynamic(Box::new({
+            let calls = calls.clone();
+
+            move || {
+                calls.set(calls.get() + 1);
+                Line::from(format!("pass {}", calls.get()))
+            }
+        }));
+
+        let text = Text(StateContext::new(), TextProps { value, ..Default::default() });
+        let props = StackProps { children: vec![Box::new(text)], ..Default::default() };
+        let mut stack = Stack(StateContext::new(), props);
+
+        let mut renderer = Renderer::new();
+        renderer.resize(10, 2);
+
+        for pass in 1..=2 {
+            stack.arrange(Rect { x: 0, y: 0, width: 10, height: 2 });
+            stack.render(&mut renderer);
+
+            assert_eq!(calls.get(), pass);
+            assert_eq!(renderer.get(5, 0).unwrap().char(), char::from_digit(pass, 10).unwrap());
+        }
     }
 }
@@ -3,15 +3,19 @@ use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::component::ComponentEvent;
+use crate::{component::ComponentEvent, state::StateUpdate};
 
 #[derive(Debug, Clone)]
 pub enum Event {
     Key(KeyEvent),
     Mouse(MouseEvent),
+    Paste(String),
+    FocusGained,
+    FocusLost,
     Tick,
     Resize(u16, u16),
     Component(ComponentEvent),
+    State(StateUpdate),
     Quit,
 }
 
@@ -20,11 +24,22 @@ pub struct EventManager {
     rx: Receiver<Event>,
 }
 
+impl Default for EventManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EventManager {
-    pub fn new(tick_rate: Duration) -> Self {
+    pub fn new() -> Self {
         let (tx, rx) = mpsc::channel();
+        Self { tx, rx }
+    }
 
-        let tx_clone = tx.clone();
+    // Spawns the thread reading terminal input and emitting ticks. Without it
+    // the manager only carries events sent through `send`.
+    pub fn listen(&self, tick_rate: Duration) {
+        let tx_clone = self.tx.clone();
 
         thread::spawn(move || {
             let mut last_tick = Instant::now();
@@ -39,7 +54,9 @@ impl EventManager {
                         CtEvent::Key(key) => tx_clone.send(Event::Key(key)).unwrap(),
                         CtEvent::Resize(w, h) => tx_clone.send(Event::Resize(w, h)).unwrap(),
                         CtEvent::Mouse(mouse) => tx_clone.send(Event::Mouse(mouse)).unwrap(),
-                        _ => {}
+                        CtEvent::Paste(text) => tx_clone.send(Event::Paste(text)).unwrap(),
+                        CtEvent::FocusGained => tx_clone.send(Event::FocusGained).unwrap(),
+                        CtEvent::FocusLost => tx_clone.send(Event::FocusLost).unwrap(),
                     }
                 }
 
@@ -49,8 +66,10 @@ impl EventManager {
                 }
             }
         });
+    }
 
-        Self { tx, rx }
+    pub fn sender(&self) -> Sender<Event> {
+        self.tx.clone()
     }
 
     pub fn send(&self, event: Event) {
@@ -60,4 +79,8 @@ impl EventManager {
     pub fn next(&self) -> Result<Event, mpsc::RecvError> {
         self.rx.recv()
     }
+
+    pub fn try_next(&self) -> Option<Event> {
+        self.rx.try_recv().ok()
+    }
 }
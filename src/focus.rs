@@ -0,0 +1,131 @@
+use crate::state::{GetState, ScopeId, StateContext};
+
+// Identifies a focusable component across renders: the scope of the component
+// that created it, and its position among the focusables created there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FocusId {
+    pub(crate) scope: ScopeId,
+    pub(crate) index: usize,
+}
+
+// Tracks the focusable components in tree order, as collected after each
+// render, and which of them has focus.
+#[derive(Default)]
+pub struct FocusManager {
+    order: Vec<FocusId>,
+    focused: Option<FocusId>,
+}
+
+impl FocusManager {
+    pub fn set_order(&mut self, order: Vec<FocusId>) {
+        if let Some(focused) = self.focused
+            && !order.contains(&focused)
+        {
+            self.focused = None;
+        }
+
+        self.order = order;
+    }
+
+    pub fn focused(&self) -> Option<FocusId> {
+        self.focused
+    }
+
+    pub fn focus(&mut self, id: Option<FocusId>) {
+        self.focused = id;
+    }
+
+    pub fn focus_next(&mut self) {
+        self.move_focus(true);
+    }
+
+    pub fn focus_previous(&mut self) {
+        self.move_focus(false);
+    }
+
+    // Moves focus one place along the order, wrapping around. Without a
+    // focused component this starts at the first or the last one.
+    fn move_focus(&mut self, forward: bool) {
+        let len = self.order.len();
+
+        if len == 0 {
+            return;
+        }
+
+        let position = self.focused
+            .and_then(|focused| self.order.iter().position(|id| *id == focused));
+
+        let next = match (position, forward) {
+            (Some(position), true) => (position + 1) % len,
+            (Some(position), false) => (position + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+
+        self.focused = Some(self.order[next]);
+    }
+}
+
+// Whether the terminal window itself has focus, as reported by the terminal.
+// Until the first report it is assumed to have.
+pub fn use_terminal_focus(ctx: StateContext) -> GetState<bool> {
+    ctx.terminal_focus()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(index: usize) -> FocusId {
+        FocusId { scope: 0, index }
+    }
+
+    fn manager(len: usize) -> FocusManager {
+        let mut manager = FocusManager::default();
+        manager.set_order((0..len).map(id).collect());
+        manager
+    }
+
+    #[test]
+    fn moving_without_focus_starts_at_either_end() {
+        let mut manager = manager(3);
+        manager.focus_next();
+        assert_eq!(manager.focused(), Some(id(0)));
+
+        let mut manager = self::manager(3);
+        manager.focus_previous();
+        assert_eq!(manager.focused(), Some(id(2)));
+    }
+
+    #[test]
+    fn moving_wraps_around() {
+        let mut manager = manager(3);
+        manager.focus(Some(id(2)));
+
+        manager.focus_next();
+        assert_eq!(manager.focused(), Some(id(0)));
+
+        manager.focus_previous();
+        assert_eq!(manager.focused(), Some(id(2)));
+    }
+
+    #[test]
+    fn moving_with_nothing_focusable_does_nothing() {
+        let mut manager = manager(0);
+        manager.focus_next();
+
+        assert_eq!(manager.focused(), None);
+    }
+
+    #[test]
+    fn focus_is_dropped_when_its_component_goes_away() {
+        let mut manager = manager(3);
+        manager.focus(Some(id(1)));
+
+        manager.set_order(vec![id(0), id(1)]);
+        assert_eq!(manager.focused(), Some(id(1)));
+
+        manager.set_order(vec![id(0)]);
+        assert_eq!(manager.focused(), None);
+    }
+}
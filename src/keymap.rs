@@ -0,0 +1,401 @@
+use std::{cell::RefCell, mem, rc::Rc, time::{Duration, Instant}};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use crate::state::{use_effect, Callback, StateContext};
+
+// A key together with the modifiers held while pressing it. Shift is ignored
+// for characters since it's already part of the character itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyCombo {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+
+        KeyCombo { code, modifiers }
+    }
+
+    pub fn ctrl(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    pub fn alt(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::ALT)
+    }
+}
+
+impl From<KeyCode> for KeyCombo {
+    fn from(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+}
+
+impl From<char> for KeyCombo {
+    fn from(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+}
+
+impl From<KeyEvent> for KeyCombo {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+// One or more combos pressed one after the other, like `g` then `g`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(Vec<KeyCombo>);
+
+impl<K: Into<KeyCombo>> From<K> for KeySequence {
+    fn from(key: K) -> Self {
+        KeySequence(vec![key.into()])
+    }
+}
+
+impl<const N: usize> From<[KeyCombo; N]> for KeySequence {
+    fn from(keys: [KeyCombo; N]) -> Self {
+        KeySequence(keys.to_vec())
+    }
+}
+
+impl From<Vec<KeyCombo>> for KeySequence {
+    fn from(keys: Vec<KeyCombo>) -> Self {
+        KeySequence(keys)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    FocusNext,
+    FocusPrevious,
+    // Handled by components through use_action.
+    Custom(&'static str),
+}
+
+// How long a started sequence waits for its next key.
+const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub(crate) enum KeyMatch {
+    Action(Action),
+    Pending,
+    // A key that isn't bound, to be handed to the components.
+    None(KeyEvent),
+}
+
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+    pending: Vec<KeyEvent>,
+    last_press: Option<Instant>,
+}
+
+impl Keymap {
+    pub fn empty() -> Self {
+        Keymap {
+            bindings: vec![],
+            pending: vec![],
+            last_press: None,
+        }
+    }
+
+    // Binding a sequence again replaces its action.
+    pub fn bind(&mut self, keys: impl Into<KeySequence>, action: Action) {
+        let keys = keys.into();
+        self.unbind(keys.clone());
+        self.bindings.push((keys, action));
+    }
+
+    pub fn unbind(&mut self, keys: impl Into<KeySequence>) {
+        let keys = keys.into();
+        self.bindings.retain(|(bound, _)| *bound != keys);
+    }
+
+    // Feeds a key press through the bindings, returning what to do with it
+    // and with any keys it releases. Keys that start a longer sequence are
+    // held back until it either completes or breaks, in which case the held
+    // keys are released and the last one is tried on its own.
+    pub(crate) fn press(&mut self, key: KeyEvent, now: Instant) -> Vec<KeyMatch> {
+        if key.kind == KeyEventKind::Release {
+            return vec![KeyMatch::None(key)];
+        }
+
+        let mut matches = self.expire(now);
+
+        self.pending.push(key);
+        self.last_press = Some(now);
+
+        if let Some(result) = self.lookup() {
+            matches.push(result);
+            return matches;
+        }
+
+        let held = mem::take(&mut self.pending);
+        matches.extend(self.release(&held[..held.len() - 1]));
+
+        if held.len() > 1 {
+            self.pending.push(key);
+
+            if let Some(result) = self.lookup() {
+                matches.push(result);
+                return matches;
+            }
+
+            self.pending.clear();
+        }
+
+        matches.push(KeyMatch::None(key));
+        matches
+    }
+
+    // Gives up on a sequence that wasn't continued in time, releasing the keys
+    // it held back.
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<KeyMatch> {
+        let expired = self.last_press
+            .is_some_and(|last_press| now.saturating_duration_since(last_press) >= SEQUENCE_TIMEOUT);
+
+        if !expired {
+            return vec![];
+        }
+
+        self.last_press = None;
+
+        let held = mem::take(&mut self.pending);
+        self.release(&held)
+    }
+
+    // Runs the actions of held keys that are bound on their own, longest
+    // binding first, and hands the rest to the components.
+    fn release(&self, mut keys: &[KeyEvent]) -> Vec<KeyMatch> {
+        let mut matches = vec![];
+
+        while !keys.is_empty() {
+            let combos: Vec<KeyCombo> = keys.iter()
+                .map(|key| KeyCombo::from(*key))
+                .collect();
+
+            let longest = self.bindings.iter()
+                .filter(|(bound, _)| !bound.0.is_empty() && combos.starts_with(&bound.0))
+                .max_by_key(|(bound, _)| bound.0.len());
+
+            match longest {
+                Some((bound, action)) => {
+                    matches.push(KeyMatch::Action(*action));
+                    keys = &keys[bound.0.len()..];
+                },
+                None => {
+                    matches.push(KeyMatch::None(keys[0]));
+                    keys = &keys[1..];
+                },
+            }
+        }
+
+        matches
+    }
+
+    // Pending when the pending keys start a longer sequence, even if they are
+    // bound on their own, so `g` doesn't shadow `g g`. Otherwise the action
+    // bound to them, or None when nothing starts with them.
+    fn lookup(&mut self) -> Option<KeyMatch> {
+        let pending: Vec<KeyCombo> = self.pending.iter()
+            .map(|key| KeyCombo::from(*key))
+            .collect();
+
+        let longer = self.bindings.iter()
+            .any(|(keys, _)| keys.0.len() > pending.len() && keys.0.starts_with(&pending));
+
+        if longer {
+            return Some(KeyMatch::Pending);
+        }
+
+        let exact = self.bindings.iter()
+            .find(|(keys, _)| keys.0 == pending)
+            .map(|(_, action)| *action);
+
+        exact.map(|action| {
+            self.pending.clear();
+            KeyMatch::Action(action)
+        })
+    }
+}
+
+// Ctrl-C quits, and Tab and Shift-Tab move focus. The arrow keys are left to
+// the components, and only move focus when none of them handles the key.
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap::empty();
+
+        keymap.bind(KeyCombo::ctrl('c'), Action::Quit);
+        keymap.bind(KeyCode::Tab, Action::FocusNext);
+        keymap.bind(KeyCode::BackTab, Action::FocusPrevious);
+
+        keymap
+    }
+}
+
+// Calls `f` whenever a key bound to `Action::Custom(name)` is pressed, for as
+// long as the component is mounted.
+pub fn use_action<F: Fn() + 'static>(ctx: StateContext, name: &'static str, f: F) {
+    let f: Rc<dyn Fn()> = Rc::new(f);
+
+    let callback: Callback = ctx.use_hook(|| RefCell::new(f.clone()));
+    *callback.borrow_mut() = f;
+
+    use_effect(ctx.clone(), {
+        let ctx = ctx.clone();
+
+        move || {
+            let id = ctx.add_action(name, callback.clone());
+
+            let ctx = ctx.clone();
+            move || ctx.remove_action(id)
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    // What each match stands for: an action, a pending sequence or a key
+    // handed on to the components.
+    fn describe(matches: Vec<KeyMatch>) -> Vec<String> {
+        matches.into_iter()
+            .map(|key_match| match key_match {
+                KeyMatch::Action(action) => format!("{action:?}"),
+                KeyMatch::Pending => "pending".to_string(),
+                KeyMatch::None(key) => format!("{:?}", key.code),
+            })
+            .collect()
+    }
+
+    fn keymap() -> Keymap {
+        let mut keymap = Keymap::empty();
+        keymap.bind('q', Action::Quit);
+        keymap.bind([KeyCombo::from('g'), KeyCombo::from('g')], Action::Custom("top"));
+        keymap.bind([KeyCombo::from('g'), KeyCombo::from('e')], Action::Custom("end"));
+        keymap
+    }
+
+    #[test]
+    fn single_keys_run_their_action() {
+        let now = Instant::now();
+        let mut keymap = keymap();
+
+        assert_eq!(describe(keymap.press(key('q'), now)), ["Quit"]);
+        assert_eq!(describe(keymap.press(key('x'), now)), ["Char('x')"]);
+    }
+
+    #[test]
+    fn sequences_are_held_until_they_complete() {
+        let now = Instant::now();
+        let mut keymap = keymap();
+
+        assert_eq!(describe(keymap.press(key('g'), now)), ["pending"]);
+        assert_eq!(describe(keymap.press(key('e'), now)), ["Custom(\"end\")"]);
+        assert!(keymap.pending.is_empty());
+    }
+
+    #[test]
+    fn broken_sequences_hand_on_the_held_keys() {
+        let now = Instant::now();
+        let mut keymap = keymap();
+
+        keymap.press(key('g'), now);
+        assert_eq!(describe(keymap.press(key('x'), now)), ["Char('g')", "Char('x')"]);
+    }
+
+    #[test]
+    fn the_key_breaking_a_sequence_is_tried_on_its_own() {
+        let now = Instant::now();
+        let mut keymap = keymap();
+
+        keymap.press(key('g'), now);
+        assert_eq!(describe(keymap.press(key('q'), now)), ["Char('g')", "Quit"]);
+
+        keymap.press(key('g'), now);
+        keymap.bind([KeyCombo::from('x'), KeyCombo::from('y')], Action::Quit);
+        assert_eq!(describe(keymap.press(key('x'), now)), ["Char('g')", "pending"]);
+    }
+
+    #[test]
+    fn pending_keys_expire() {
+        let now = Instant::now();
+        let mut keymap = keymap();
+
+        keymap.press(key('g'), now);
+        assert!(keymap.expire(now + SEQUENCE_TIMEOUT / 2).is_empty());
+        assert_eq!(describe(keymap.expire(now + SEQUENCE_TIMEOUT)), ["Char('g')"]);
+
+        // The next g starts a new sequence rather than completing the old one
+        let later = now + SEQUENCE_TIMEOUT * 2;
+        assert_eq!(describe(keymap.press(key('g'), later)), ["pending"]);
+        assert_eq!(describe(keymap.press(key('g'), later + SEQUENCE_TIMEOUT)), ["Char('g')", "pending"]);
+    }
+
+    #[test]
+    fn a_bound_key_waits_for_the_sequence_it_starts() {
+        let now = Instant::now();
+        let mut keymap = keymap();
+        keymap.bind('g', Action::Custom("g"));
+
+        assert_eq!(describe(keymap.press(key('g'), now)), ["pending"]);
+        assert_eq!(describe(keymap.press(key('g'), now)), ["Custom(\"top\")"]);
+
+        keymap.press(key('g'), now);
+        assert_eq!(describe(keymap.expire(now + SEQUENCE_TIMEOUT)), ["Custom(\"g\")"]);
+
+        keymap.press(key('g'), now);
+        assert_eq!(describe(keymap.press(key('x'), now)), ["Custom(\"g\")", "Char('x')"]);
+    }
+
+    #[test]
+    fn releases_pass_through() {
+        let mut keymap = keymap();
+        let mut release = key('q');
+        release.kind = KeyEventKind::Release;
+
+        assert_eq!(describe(keymap.press(release, Instant::now())), ["Char('q')"]);
+    }
+
+    #[test]
+    fn shift_is_ignored_for_characters() {
+        assert_eq!(KeyCombo::new(KeyCode::Char('G'), KeyModifiers::SHIFT), KeyCombo::from('G'));
+        assert_ne!(KeyCombo::new(KeyCode::Up, KeyModifiers::SHIFT), KeyCombo::from(KeyCode::Up));
+    }
+
+    #[test]
+    fn binding_again_replaces_the_action() {
+        let mut keymap = keymap();
+        keymap.bind('q', Action::Custom("query"));
+
+        assert_eq!(describe(keymap.press(key('q'), Instant::now())), ["Custom(\"query\")"]);
+
+        keymap.unbind('q');
+        assert_eq!(describe(keymap.press(key('q'), Instant::now())), ["Char('q')"]);
+    }
+
+    #[test]
+    fn arrows_are_not_bound_by_default() {
+        let mut keymap = Keymap::default();
+        let now = Instant::now();
+
+        for code in [KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right] {
+            let matches = keymap.press(KeyEvent::new(code, KeyModifiers::NONE), now);
+            assert_eq!(describe(matches), [format!("{code:?}")]);
+        }
+
+        let matches = keymap.press(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE), now);
+        assert_eq!(describe(matches), ["FocusNext"]);
+    }
+}
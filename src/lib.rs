@@ -5,7 +5,15 @@ pub mod marcos {
 pub mod screen_buffer;
 pub mod app;
 pub mod renderer;
+pub mod backend;
 pub mod events;
 pub mod component;
 pub mod state;
+pub mod focus;
+pub mod keymap;
+pub mod style;
+pub mod line;
+pub mod resource;
+pub mod timer;
 pub mod prelude;
+pub mod testing;
@@ -0,0 +1,296 @@
+use std::{fmt, mem};
+
+use crate::{screen_buffer::display_width, style::{Color, Style}};
+
+// A piece of text drawn in one style, on top of the style of the component
+// showing it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub content: String,
+    pub style: Style,
+}
+
+impl Span {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            style: Style::new(),
+        }
+    }
+
+    pub fn styled(content: impl Into<String>, style: Style) -> Self {
+        Self {
+            content: content.into(),
+            style,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        display_width(&self.content)
+    }
+}
+
+impl From<String> for Span {
+    fn from(content: String) -> Self {
+        Self::new(content)
+    }
+}
+
+impl From<&str> for Span {
+    fn from(content: &str) -> Self {
+        Self::new(content)
+    }
+}
+
+// A row of spans, which is what a Text shows.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    pub fn new(spans: Vec<Span>) -> Self {
+        Self { spans }
+    }
+
+    pub fn push(mut self, span: impl Into<Span>) -> Self {
+        self.spans.push(span.into());
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.spans.iter()
+            .map(Span::width)
+            .sum()
+    }
+
+    // Parses text with inline style tags, like "[bold red]Error:[/] not found".
+    // A tag holds attributes (bold, italic, underline, dim, reverse), a colour
+    // name or #rrggbb for the foreground, and "on" followed by a colour for the
+    // background. [/] closes the last open tag and [[ is a literal bracket.
+    // Brackets that don't hold a valid tag are left in the text.
+    pub fn markup(text: &str) -> Self {
+        let mut line = Line::default();
+        let mut styles: Vec<Style> = vec![];
+        let mut content = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find('[') {
+            content.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if let Some(after) = rest.strip_prefix("[[") {
+                content.push('[');
+                rest = after;
+                continue;
+            }
+
+            let Some(end) = rest.find(']') else {
+                break;
+            };
+
+            // Another [ before the ] starts the tag, so this one is text
+            if rest[1..end].contains('[') {
+                content.push('[');
+                rest = &rest[1..];
+                continue;
+            }
+
+            let current = styles.last().copied().unwrap_or_default();
+
+            match parse_tag(&rest[1..end]) {
+                Some(tag) => {
+                    if !content.is_empty() {
+                        line.spans.push(Span::styled(mem::take(&mut content), current));
+                    }
+
+                    match tag {
+                        Tag::Open(style) => styles.push(style.inherit(&current)),
+                        Tag::Close => { styles.pop(); },
+                    }
+                },
+                None => content.push_str(&rest[..=end]),
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        content.push_str(rest);
+
+        if !content.is_empty() {
+            line.spans.push(Span::styled(content, styles.last().copied().unwrap_or_default()));
+        }
+
+        line
+    }
+}
+
+enum Tag {
+    Open(Style),
+    Close,
+}
+
+fn parse_tag(tag: &str) -> Option<Tag> {
+    if tag == "/" {
+        return Some(Tag::Close);
+    }
+
+    if tag.trim().is_empty() {
+        return None;
+    }
+
+    let mut style = Style::new();
+    let mut words = tag.split_whitespace();
+
+    while let Some(word) = words.next() {
+        style = match word {
+            "bold" => style.bold(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            "dim" => style.dim(),
+            "reverse" => style.reverse(),
+            "on" => style.bg(parse_color(words.next()?)?),
+            _ => style.fg(parse_color(word)?),
+        };
+    }
+
+    Some(Tag::Open(style))
+}
+
+fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16).ok()
+            .filter(|_| hex.len() == 6)?;
+
+        return Some(Color::Rgb {
+            r: (value >> 16) as u8,
+            g: (value >> 8) as u8,
+            b: value as u8,
+        });
+    }
+
+    Color::try_from(name).ok()
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for span in &self.spans {
+            f.write_str(&span.content)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<String> for Line {
+    fn from(content: String) -> Self {
+        Self::new(vec![Span::new(content)])
+    }
+}
+
+impl From<&str> for Line {
+    fn from(content: &str) -> Self {
+        Self::new(vec![Span::new(content)])
+    }
+}
+
+impl From<Span> for Line {
+    fn from(span: Span) -> Self {
+        Self::new(vec![span])
+    }
+}
+
+impl From<Vec<Span>> for Line {
+    fn from(spans: Vec<Span>) -> Self {
+        Self::new(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(tag: &str) -> Option<Style> {
+        match parse_tag(tag)? {
+            Tag::Open(style) => Some(style),
+            Tag::Close => None,
+        }
+    }
+
+    #[test]
+    fn tags_hold_attributes_and_colours() {
+        assert_eq!(open("bold"), Some(Style::new().bold()));
+        assert_eq!(open("italic underline dim reverse"), Some(Style::new().italic().underline().dim().reverse()));
+        assert_eq!(open("red"), Some(Style::new().fg(Color::Red)));
+        assert_eq!(open("bold red on blue"), Some(Style::new().bold().fg(Color::Red).bg(Color::Blue)));
+        assert_eq!(open("#ff8000"), Some(Style::new().fg(Color::Rgb { r: 255, g: 128, b: 0 })));
+    }
+
+    #[test]
+    fn a_slash_closes_a_tag() {
+        assert!(matches!(parse_tag("/"), Some(Tag::Close)));
+    }
+
+    #[test]
+    fn anything_else_is_not_a_tag() {
+        assert!(parse_tag("").is_none());
+        assert!(parse_tag("  ").is_none());
+        assert!(parse_tag("foo").is_none());
+        assert!(parse_tag("bold on").is_none());
+        assert!(parse_tag("#ff80").is_none());
+        assert!(parse_tag("#gggggg").is_none());
+    }
+
+    #[test]
+    fn markup_splits_text_into_styled_spans() {
+        let line = Line::markup("[bold red]Error:[/] not found");
+
+        assert_eq!(line.spans, [
+            Span::styled("Error:", Style::new().bold().fg(Color::Red)),
+            Span::new(" not found"),
+        ]);
+    }
+
+    #[test]
+    fn nested_tags_inherit_from_the_enclosing_one() {
+        let line = Line::markup("[bold]a[red]b[/]c");
+
+        assert_eq!(line.spans, [
+            Span::styled("a", Style::new().bold()),
+            Span::styled("b", Style::new().bold().fg(Color::Red)),
+            Span::styled("c", Style::new().bold()),
+        ]);
+    }
+
+    #[test]
+    fn doubled_brackets_are_literal() {
+        assert_eq!(Line::markup("[[bold]").to_string(), "[bold]");
+        assert_eq!(Line::markup("a [[b] c").spans, [Span::new("a [b] c")]);
+    }
+
+    #[test]
+    fn unknown_and_unclosed_tags_stay_as_text() {
+        assert_eq!(Line::markup("[foo]x").spans, [Span::new("[foo]x")]);
+        assert_eq!(Line::markup("x[bold").spans, [Span::new("x[bold")]);
+    }
+
+    #[test]
+    fn tags_start_at_the_nearest_bracket() {
+        let line = Line::markup("[foo [bold]x");
+
+        assert_eq!(line.spans, [
+            Span::new("[foo "),
+            Span::styled("x", Style::new().bold()),
+        ]);
+
+        assert_eq!(Line::markup("[x [[y]").spans, [Span::new("[x [y]")]);
+    }
+
+    #[test]
+    fn extra_closing_tags_are_ignored() {
+        let line = Line::markup("a[/]b");
+
+        assert_eq!(line.to_string(), "ab");
+        assert!(line.spans.iter().all(|span| span.style == Style::new()));
+    }
+}
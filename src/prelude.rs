@@ -2,10 +2,19 @@
 
 pub use tolid_macros::*;
 pub use crate::state::*;
+pub use crate::resource::*;
+pub use crate::timer::*;
+pub use crate::keymap::*;
+pub use crate::focus::*;
+pub use crate::style::*;
+pub use crate::line::*;
 pub use crate::component::*;
 pub use crate::component::stack::*;
 pub use crate::component::text::*;
 pub use crate::component::center::*;
 pub use crate::component::button::*;
+pub use crate::component::provider::*;
+pub use crate::component::mouse::*;
+pub use crate::component::event::*;
 pub use crate::app::*;
 pub use crate::cm;
@@ -1,8 +1,8 @@
-use std::{io::{Stdout, Write}, ops::Range, mem};
+use std::{ops::Range, mem};
 
-use crossterm::{cursor::MoveTo, style::{Attributes, Color, ContentStyle, Print, SetAttributes, SetBackgroundColor, SetForegroundColor, SetUnderlineColor}, QueueableCommand};
+use crossterm::style::{Attribute, Attributes, Color, ContentStyle};
 
-use crate::screen_buffer::{BufferCell, ScreenBuffer};
+use crate::{backend::Backend, screen_buffer::{BufferCell, ScreenBuffer}, style::Style};
 
 type BoxCharLayout = u8;
 
@@ -153,6 +153,13 @@ pub struct Renderer {
 
     render_context_global: RenderContext,
     render_context_stack: Vec<RenderContext>,
+    style_stack: Vec<Style>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Renderer {
@@ -170,6 +177,7 @@ impl Renderer {
 
          render_context_global: RenderContext { x: 0, y: 0, width: 0, height: 0 },
          render_context_stack: vec![],
+         style_stack: vec![],
         }
     }
 
@@ -212,6 +220,23 @@ impl Renderer {
             .unwrap_or(&self.render_context_global)
     }
 
+    // Styles pushed while rendering a component apply to its children too,
+    // for whatever they don't set themselves.
+    pub fn push_style(&mut self, style: Style) {
+        let style = style.inherit(&self.current_style());
+        self.style_stack.push(style);
+    }
+
+    pub fn pop_style(&mut self) {
+        self.style_stack.pop();
+    }
+
+    pub fn current_style(&self) -> Style {
+        self.style_stack.last()
+            .copied()
+            .unwrap_or_default()
+    }
+
     pub fn get(&self, x: usize, y: usize) -> Option<&BufferCell> {
         let render_context = self.current_render_context();
 
@@ -232,6 +257,13 @@ impl Renderer {
             return;
         }
 
+        // A double width grapheme cut off by the edge shows as a space
+        let cell = if cell.width() == 2 && x + 1 >= render_context.width {
+            BufferCell::new(*cell.style(), ' ')
+        } else {
+            cell
+        };
+
         let x = x + render_context.x;
         let y = y + render_context.y;
 
@@ -242,6 +274,17 @@ impl Renderer {
         (self.width, self.height)
     }
 
+    // Clears the area to spaces in the current style.
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        let style = self.current_style().content_style();
+
+        for y in y..y+height {
+            for x in x..x+width {
+                self.set(x, y, BufferCell::new(style, ' '));
+            }
+        }
+    }
+
     pub fn draw_box(&mut self, x: usize, y: usize, width: usize, height: usize) {
         self.draw_v_capped_line(x, y, height);
         self.draw_v_capped_line(x+width-1, y, height);
@@ -277,52 +320,59 @@ impl Renderer {
 
     pub fn draw_box_char(&mut self, x: usize, y: usize, box_char: BoxCharLayout) {
         if let Some(c) = self.get(x, y) {
-            let new_char = BOX_CHAR_LAYOUT_MAP.overlap_char(*c.content(), box_char);
-            self.set(x, y, BufferCell::new(*c.style(), new_char));
+            let new_char = BOX_CHAR_LAYOUT_MAP.overlap_char(c.char(), box_char);
+            let style = self.current_style().content_style();
+            self.set(x, y, BufferCell::new(style, new_char));
         }
     }
 
-    pub fn render(&mut self, stdout: &mut Stdout) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn apply_attribute(&mut self, x: usize, y: usize, width: usize, height: usize, attribute: Attribute) {
+        for y in y..y+height {
+            for x in x..x+width {
+                if let Some(c) = self.get(x, y) {
+                    let mut style = *c.style();
+                    style.attributes.set(attribute);
+                    self.set(x, y, c.with_style(style));
+                }
+            }
+        }
+    }
+
+    pub fn render<B: Backend + ?Sized>(&mut self, backend: &mut B) -> Result<(), Box<dyn std::error::Error>> {
         for y in 0..self.height {
-            let mut start: Option<(usize, BufferCell)> = None;
+            let mut start: Option<(usize, ContentStyle)> = None;
 
             for x in 0..self.width {
-                let current_cell = self.current_buffer.get(x, y).copied();
-                let previous_cell = self.previous_buffer.get(x, y).copied();
+                let current_cell = self.current_buffer.get(x, y).cloned();
+                let previous_cell = self.previous_buffer.get(x, y).cloned();
 
                 if let (Some(current_cell), Some(previous_cell)) = (current_cell, previous_cell) {
                     if current_cell == previous_cell {
-                        if let Some((start_index, start_cell)) = start {
-                            let style = start_cell.style();
-                            let content = self.slice_string(start_index..x, y);
-                            self.queue_styled_string(stdout, start_index, y, style, &content)?;
+                        if let Some((start_index, start_style)) = start {
+                            self.queue_run(backend, start_index..x, y, &start_style)?;
 
                             start = None;
                         }
                     } else {
-                        if let Some((start_index, start_cell)) = start {
-                            if start_cell.style() != current_cell.style() {
-                                let style = start_cell.style();
-                                let content = self.slice_string(start_index..x, y);
-                                self.queue_styled_string(stdout, start_index, y, style, &content)?;
+                        if let Some((start_index, start_style)) = start {
+                            if &start_style != current_cell.style() {
+                                self.queue_run(backend, start_index..x, y, &start_style)?;
 
-                                start = Some((x, current_cell));
+                                start = Some((x, *current_cell.style()));
                             }
                         } else {
-                            start = Some((x, current_cell));
+                            start = Some((x, *current_cell.style()));
                         }
                     }
                 }
             }
 
-            if let Some((start_index, start_cell)) = start {
-                let style = start_cell.style();
-                let content = self.slice_string(start_index..self.width, y);
-                self.queue_styled_string(stdout, start_index, y, style, &content)?;
+            if let Some((start_index, start_style)) = start {
+                self.queue_run(backend, start_index..self.width, y, &start_style)?;
             }
         }
 
-        stdout.flush()?;
+        backend.flush()?;
 
         mem::swap(&mut self.previous_buffer, &mut self.current_buffer);
         self.current_buffer.clear();
@@ -330,44 +380,180 @@ impl Renderer {
         Ok(())
     }
 
-    fn queue_styled_string(&mut self, stdout: &mut Stdout, x: usize, y: usize, style: &ContentStyle, content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // Continuation cells print nothing, so a run starting with one is written
+    // from the first cell after it.
+    fn queue_run<B: Backend + ?Sized>(&mut self, backend: &mut B, x_range: Range<usize>, y: usize, style: &ContentStyle) -> Result<(), Box<dyn std::error::Error>> {
+        let start = x_range.clone()
+            .find(|x| !self.current_buffer.get(*x, y).is_some_and(BufferCell::is_continuation))
+            .unwrap_or(x_range.end);
+
+        let content = self.slice_string(start..x_range.end, y);
+        self.queue_styled_string(backend, start, y, style, &content)
+    }
+
+    fn queue_styled_string<B: Backend + ?Sized>(&mut self, backend: &mut B, x: usize, y: usize, style: &ContentStyle, content: &str) -> Result<(), Box<dyn std::error::Error>> {
         let foreground_color = style.foreground_color.unwrap_or(Color::Reset);
         let background_color = style.background_color.unwrap_or(Color::Reset);
         let underline_color = style.underline_color.unwrap_or(Color::Reset);
         let attributes = style.attributes;
 
-        stdout.queue(MoveTo(x as u16, y as u16))?;
+        backend.move_cursor(x as u16, y as u16)?;
 
         if foreground_color != self.current_foreground_color {
-            stdout.queue(SetForegroundColor(foreground_color))?;
+            backend.set_foreground_color(foreground_color)?;
             self.current_foreground_color = foreground_color;
         }
 
         if background_color != self.current_background_color {
-            stdout.queue(SetBackgroundColor(background_color))?;
+            backend.set_background_color(background_color)?;
             self.current_background_color = background_color;
         }
 
         if underline_color != self.current_underline_color {
-            stdout.queue(SetUnderlineColor(underline_color))?;
+            backend.set_underline_color(underline_color)?;
             self.current_underline_color = underline_color;
         }
 
         if attributes != self.current_attributes {
-            stdout.queue(SetAttributes(attributes))?;
+            backend.set_attributes(attributes)?;
             self.current_attributes = attributes;
         }
 
-        stdout.queue(Print(content))?;
+        backend.print(content)?;
 
         Ok(())
     }
 
     fn slice_string(&self, x_range: Range<usize>, y: usize) -> String {
         if let Some(row) = self.current_buffer.row(y) {
-            row[x_range].iter().map(|cell| cell.content()).collect()
+            let mut content = String::new();
+
+            for cell in &row[x_range] {
+                cell.push_to(&mut content);
+            }
+
+            content
         } else {
             String::new()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::*;
+
+    // Records where each string is printed.
+    #[derive(Default)]
+    struct Recorder {
+        cursor: (usize, usize),
+        prints: Vec<(usize, usize, String)>,
+    }
+
+    impl Backend for Recorder {
+        fn size(&self) -> io::Result<(u16, u16)> { Ok((0, 0)) }
+        fn enable_raw_mode(&mut self) -> io::Result<()> { Ok(()) }
+        fn disable_raw_mode(&mut self) -> io::Result<()> { Ok(()) }
+        fn enter_alternate_screen(&mut self) -> io::Result<()> { Ok(()) }
+        fn leave_alternate_screen(&mut self) -> io::Result<()> { Ok(()) }
+        fn enable_mouse_capture(&mut self) -> io::Result<()> { Ok(()) }
+        fn disable_mouse_capture(&mut self) -> io::Result<()> { Ok(()) }
+        fn enable_bracketed_paste(&mut self) -> io::Result<()> { Ok(()) }
+        fn disable_bracketed_paste(&mut self) -> io::Result<()> { Ok(()) }
+        fn enable_focus_change(&mut self) -> io::Result<()> { Ok(()) }
+        fn disable_focus_change(&mut self) -> io::Result<()> { Ok(()) }
+        fn hide_cursor(&mut self) -> io::Result<()> { Ok(()) }
+        fn show_cursor(&mut self) -> io::Result<()> { Ok(()) }
+        fn set_foreground_color(&mut self, _color: Color) -> io::Result<()> { Ok(()) }
+        fn set_background_color(&mut self, _color: Color) -> io::Result<()> { Ok(()) }
+        fn set_underline_color(&mut self, _color: Color) -> io::Result<()> { Ok(()) }
+        fn set_attributes(&mut self, _attributes: Attributes) -> io::Result<()> { Ok(()) }
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+
+        fn move_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+            self.cursor = (x as usize, y as usize);
+            Ok(())
+        }
+
+        fn print(&mut self, content: &str) -> io::Result<()> {
+            self.prints.push((self.cursor.0, self.cursor.1, content.to_string()));
+            Ok(())
+        }
+    }
+
+    fn renderer(width: usize) -> Renderer {
+        let mut renderer = Renderer::new();
+        renderer.resize(width, 1);
+        renderer
+    }
+
+    fn write(renderer: &mut Renderer, text: &str) {
+        let mut x = 0;
+
+        for grapheme in text.graphemes(true) {
+            let cell = BufferCell::grapheme(ContentStyle::new(), grapheme);
+            let width = cell.width();
+
+            renderer.set(x, 0, cell);
+            x += width;
+        }
+    }
+
+    fn frame(renderer: &mut Renderer, text: &str) -> Vec<(usize, usize, String)> {
+        let mut recorder = Recorder::default();
+
+        write(renderer, text);
+        renderer.render(&mut recorder).unwrap();
+
+        recorder.prints
+    }
+
+    fn print(x: usize, content: &str) -> (usize, usize, String) {
+        (x, 0, content.to_string())
+    }
+
+    #[test]
+    fn only_changed_runs_are_printed() {
+        let mut renderer = renderer(6);
+
+        assert_eq!(frame(&mut renderer, "abcdef"), [print(0, "abcdef")]);
+        assert_eq!(frame(&mut renderer, "abXdeY"), [print(2, "X"), print(5, "Y")]);
+        assert_eq!(frame(&mut renderer, "abXdeY"), []);
+    }
+
+    #[test]
+    fn runs_print_each_wide_grapheme_once() {
+        let mut renderer = renderer(6);
+
+        assert_eq!(frame(&mut renderer, "日本x"), [print(0, "日本x")]);
+        assert_eq!(frame(&mut renderer, "日語x"), [print(2, "語")]);
+    }
+
+    #[test]
+    fn runs_starting_on_a_continuation_print_from_the_next_cell() {
+        let mut renderer = renderer(4);
+        frame(&mut renderer, "日y");
+
+        // Only the second half of 日 and the x after it change
+        write(&mut renderer, "日x");
+        renderer.apply_attribute(1, 0, 2, 1, Attribute::Reverse);
+
+        let mut recorder = Recorder::default();
+        renderer.render(&mut recorder).unwrap();
+
+        assert_eq!(recorder.prints, [print(2, "x")]);
+    }
+
+    #[test]
+    fn replacing_a_wide_grapheme_reprints_both_its_columns() {
+        let mut renderer = renderer(4);
+        frame(&mut renderer, "日x");
+
+        assert_eq!(frame(&mut renderer, "abx"), [print(0, "ab")]);
+        assert_eq!(frame(&mut renderer, "日x"), [print(0, "日")]);
+    }
+}
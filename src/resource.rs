@@ -0,0 +1,118 @@
+use std::{any::Any, cell::Cell, panic::{self, AssertUnwindSafe}, sync::{atomic::{AtomicBool, Ordering}, Arc, Once}, thread};
+
+use crate::state::{use_effect, use_state, GetState, StateContext};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resource<T, E> {
+    Loading,
+    Ready(T),
+    Failed(E),
+    // The loader panicked, with the panic's message. Kept apart from Failed so
+    // the loader's own error type doesn't have to make room for panics.
+    Panicked(String),
+}
+
+impl<T, E> Resource<T, E> {
+    pub fn is_loading(&self) -> bool {
+        matches!(self, Resource::Loading)
+    }
+
+    pub fn ready(&self) -> Option<&T> {
+        match self {
+            Resource::Ready(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+thread_local! {
+    static LOADING: Cell<bool> = const { Cell::new(false) };
+}
+
+// A loader's panic is reported through its resource, so it isn't printed over
+// the UI as well. Panics anywhere else still go to the hook set before.
+fn silence_loader_panics() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if !LOADING.get() {
+                previous(info);
+            }
+        }));
+    });
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast::<&str>()
+            .map(|message| message.to_string())
+            .unwrap_or_else(|_| "loader panicked".to_string()),
+    }
+}
+
+// Loads data on a background thread. `source` runs on the UI thread, where it
+// can read state, and returns the loader to run on the worker. Whenever state
+// read by `source` changes the resource goes back to Loading and is fetched
+// again, with results of superseded loads being dropped. Outside of a running
+// app nothing is loaded.
+pub fn use_resource<T, E, F, L>(ctx: StateContext, source: F) -> GetState<Resource<T, E>>
+where
+    T: Clone + Send + 'static,
+    E: Clone + Send + 'static,
+    F: Fn() -> L + 'static,
+    L: FnOnce() -> Result<T, E> + Send + 'static,
+{
+    let (resource, set_resource) = use_state(ctx.clone(), Resource::Loading);
+
+    use_effect(ctx.clone(), {
+        let resource = resource.clone();
+
+        move || {
+            let load = source();
+
+            if !resource.get_untracked().is_loading() {
+                set_resource.set(Resource::Loading);
+            }
+
+            // Results are delivered through the event loop of a running app
+            let sender = set_resource.sender()?;
+            let cancelled = Arc::new(AtomicBool::new(false));
+
+            silence_loader_panics();
+
+            thread::spawn({
+                let cancelled = cancelled.clone();
+
+                move || {
+                    LOADING.set(true);
+                    let result = panic::catch_unwind(AssertUnwindSafe(load));
+                    LOADING.set(false);
+
+                    let result = match result {
+                        Ok(Ok(value)) => Resource::Ready(value),
+                        Ok(Err(error)) => Resource::Failed(error),
+                        Err(payload) => Resource::Panicked(panic_message(payload)),
+                    };
+
+                    if cancelled.load(Ordering::SeqCst) {
+                        return;
+                    }
+
+                    // Checked again on the UI thread, in case the load was
+                    // cancelled while the result was on its way
+                    sender.try_update(move |_| {
+                        (!cancelled.load(Ordering::SeqCst)).then_some(result)
+                    });
+                }
+            });
+
+            Some(move || cancelled.store(true, Ordering::SeqCst))
+        }
+    });
+
+    resource
+}
@@ -1,10 +1,95 @@
-use crossterm::style::{ContentStyle, StyledContent};
+use crossterm::style::ContentStyle;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-pub type BufferCell = StyledContent<char>;
 const EMPTY_CHAR: char = ' ';
 
 pub fn empty_cell() -> BufferCell {
-    ContentStyle::new().apply(EMPTY_CHAR)
+    BufferCell::new(ContentStyle::new(), EMPTY_CHAR)
+}
+
+// The number of columns a grapheme cluster takes up in the terminal.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width().min(2)
+}
+
+// The number of columns a string takes up, measured one grapheme at a time.
+pub fn display_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Symbol {
+    Char(char),
+    Grapheme(Box<str>),
+    // The second column of a double width grapheme in the cell before it
+    Continuation,
+}
+
+// A single column of the screen. A grapheme two columns wide is stored in its
+// first cell and followed by a continuation cell, which prints nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferCell {
+    symbol: Symbol,
+    style: ContentStyle,
+}
+
+impl BufferCell {
+    pub fn new(style: ContentStyle, c: char) -> Self {
+        Self { symbol: Symbol::Char(c), style }
+    }
+
+    pub fn grapheme(style: ContentStyle, grapheme: &str) -> Self {
+        let mut chars = grapheme.chars();
+
+        let symbol = match (chars.next(), chars.next()) {
+            (Some(c), None) => Symbol::Char(c),
+            _ => Symbol::Grapheme(grapheme.into()),
+        };
+
+        Self { symbol, style }
+    }
+
+    pub fn continuation(style: ContentStyle) -> Self {
+        Self { symbol: Symbol::Continuation, style }
+    }
+
+    pub fn style(&self) -> &ContentStyle {
+        &self.style
+    }
+
+    pub fn with_style(&self, style: ContentStyle) -> Self {
+        Self { symbol: self.symbol.clone(), style }
+    }
+
+    // The first char of the grapheme, or a space for a continuation.
+    pub fn char(&self) -> char {
+        match &self.symbol {
+            Symbol::Char(c) => *c,
+            Symbol::Grapheme(grapheme) => grapheme.chars().next().unwrap_or(EMPTY_CHAR),
+            Symbol::Continuation => EMPTY_CHAR,
+        }
+    }
+
+    pub fn push_to(&self, text: &mut String) {
+        match &self.symbol {
+            Symbol::Char(c) => text.push(*c),
+            Symbol::Grapheme(grapheme) => text.push_str(grapheme),
+            Symbol::Continuation => {},
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match &self.symbol {
+            Symbol::Char(c) => c.width().unwrap_or(0).min(2),
+            Symbol::Grapheme(grapheme) => grapheme_width(grapheme),
+            Symbol::Continuation => 0,
+        }
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.symbol == Symbol::Continuation
+    }
 }
 
 pub struct ScreenBuffer {
@@ -13,6 +98,12 @@ pub struct ScreenBuffer {
     height: usize,
 }
 
+impl Default for ScreenBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ScreenBuffer {
     pub fn new() -> Self {
         Self {
@@ -28,6 +119,14 @@ impl ScreenBuffer {
         self.height = height;
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn clear(&mut self) {
         self.buffer.fill(empty_cell());
     }
@@ -50,10 +149,56 @@ impl ScreenBuffer {
         }
     }
 
+    // Sets a cell, keeping double width graphemes whole. One that no longer
+    // fits in the row is replaced by a space, and one partly overwritten has
+    // its other half blanked.
     pub fn set(&mut self, x: usize, y: usize, cell: BufferCell) {
-        if x < self.width && y < self.height {
-            self.buffer[y * self.width + x] = cell;
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let wide = cell.width() == 2;
+
+        if wide && x + 1 >= self.width {
+            let style = *cell.style();
+            self.put(x, y, BufferCell::new(style, EMPTY_CHAR));
+            return;
+        }
+
+        self.put(x, y, cell.clone());
+
+        if wide {
+            self.put(x + 1, y, BufferCell::continuation(*cell.style()));
+        }
+    }
+
+    fn put(&mut self, x: usize, y: usize, cell: BufferCell) {
+        let i = y * self.width + x;
+        let was_continuation = self.buffer[i].is_continuation();
+        let was_wide = self.buffer[i].width() == 2;
+
+        if was_continuation && !cell.is_continuation() && x > 0 {
+            let style = *self.buffer[i - 1].style();
+            self.buffer[i - 1] = BufferCell::new(style, EMPTY_CHAR);
+        }
+
+        if was_wide && x + 1 < self.width && self.buffer[i + 1].is_continuation() {
+            let style = *self.buffer[i + 1].style();
+            self.buffer[i + 1] = BufferCell::new(style, EMPTY_CHAR);
+        }
+
+        self.buffer[i] = cell;
+    }
+
+    // The text shown on a row, with each grapheme appearing once.
+    pub fn row_text(&self, y: usize) -> String {
+        let mut text = String::new();
+
+        for cell in self.row(y).unwrap_or_default() {
+            cell.push_to(&mut text);
         }
+
+        text
     }
 
     pub fn row(&self, y: usize) -> Option<&[BufferCell]> {
@@ -66,3 +211,100 @@ impl ScreenBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(width: usize) -> ScreenBuffer {
+        let mut buffer = ScreenBuffer::new();
+        buffer.resize(width, 1);
+        buffer
+    }
+
+    fn cell(grapheme: &str) -> BufferCell {
+        BufferCell::grapheme(ContentStyle::new(), grapheme)
+    }
+
+    #[test]
+    fn widths_are_measured_per_grapheme() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本"), 4);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👍🏽"), 2);
+
+        assert_eq!(cell("日").width(), 2);
+        assert_eq!(cell("e\u{301}").width(), 1);
+        assert_eq!(BufferCell::continuation(ContentStyle::new()).width(), 0);
+    }
+
+    #[test]
+    fn wide_graphemes_are_followed_by_a_continuation() {
+        let mut buffer = buffer(4);
+        buffer.set(1, 0, cell("日"));
+
+        assert_eq!(buffer.get(1, 0).unwrap().char(), '日');
+        assert!(buffer.get(2, 0).unwrap().is_continuation());
+        assert_eq!(buffer.row_text(0), " 日 ");
+    }
+
+    #[test]
+    fn wide_graphemes_that_dont_fit_become_spaces() {
+        let mut buffer = buffer(4);
+        buffer.set(3, 0, cell("日"));
+
+        assert_eq!(buffer.row_text(0), "    ");
+        assert!(!buffer.get(3, 0).unwrap().is_continuation());
+    }
+
+    #[test]
+    fn overwriting_the_first_half_blanks_the_second() {
+        let mut buffer = buffer(4);
+        buffer.set(0, 0, cell("日"));
+        buffer.set(0, 0, cell("a"));
+
+        assert_eq!(buffer.row_text(0), "a   ");
+        assert!(!buffer.get(1, 0).unwrap().is_continuation());
+    }
+
+    #[test]
+    fn overwriting_the_second_half_blanks_the_first() {
+        let mut buffer = buffer(4);
+        buffer.set(0, 0, cell("日"));
+        buffer.set(1, 0, cell("b"));
+
+        assert_eq!(buffer.row_text(0), " b  ");
+    }
+
+    #[test]
+    fn overlapping_wide_graphemes_leave_no_halves() {
+        let mut buffer = buffer(4);
+        buffer.set(0, 0, cell("日"));
+        buffer.set(2, 0, cell("本"));
+        buffer.set(1, 0, cell("語"));
+
+        assert_eq!(buffer.row_text(0), " 語 ");
+        assert!(buffer.get(2, 0).unwrap().is_continuation());
+        assert!(!buffer.get(3, 0).unwrap().is_continuation());
+    }
+
+    #[test]
+    fn cells_outside_the_buffer_are_ignored() {
+        let mut buffer = buffer(2);
+        buffer.set(2, 0, cell("a"));
+        buffer.set(0, 1, cell("a"));
+
+        assert_eq!(buffer.row_text(0), "  ");
+        assert!(buffer.get(2, 0).is_none());
+    }
+
+    #[test]
+    fn clusters_are_kept_whole() {
+        let mut buffer = buffer(4);
+        buffer.set(0, 0, cell("e\u{301}"));
+        buffer.set(1, 0, cell("👍🏽"));
+
+        assert_eq!(buffer.get(0, 0).unwrap().char(), 'e');
+        assert_eq!(buffer.row_text(0), "e\u{301}👍🏽 ");
+    }
+}
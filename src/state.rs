@@ -1,12 +1,36 @@
-use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use std::any::Any;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{mpsc::Sender, Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::rc::{Rc, Weak};
 use std::cell::{Cell, RefCell};
 
-#[derive(Clone, Default)]
+use crate::{events::Event, focus::{FocusId, FocusManager}};
+
+#[derive(Default)]
 pub struct InnerState<T> {
     value: Rc<RefCell<T>>,
     ctx: StateContext,
     subscribers: Rc<RefCell<HashSet<usize>>>,
+    // The scope that created the state, which drops its remote setter when
+    // the component is unmounted.
+    owner: Option<ScopeId>,
+    // Given out by the context when the first sender is created.
+    remote_id: Rc<Cell<Option<usize>>>,
+}
+
+impl<T> Clone for InnerState<T> {
+    fn clone(&self) -> Self {
+        InnerState {
+            value: self.value.clone(),
+            ctx: self.ctx.clone(),
+            subscribers: self.subscribers.clone(),
+            owner: self.owner,
+            remote_id: self.remote_id.clone(),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -22,10 +46,13 @@ impl<T: Default> Default for GetState<T> {
 
 impl<T: Clone> GetState<T> {
     pub fn get(&self) -> T {
-        if let Some(id) = *self.inner.ctx.current_effect.borrow() {
-            self.inner.subscribers.borrow_mut().insert(id);
-        }
+        self.inner.ctx.track(&self.inner.subscribers);
+
+        self.inner.value.borrow().clone()
+    }
 
+    // Reads the value without subscribing the running effect to it.
+    pub fn get_untracked(&self) -> T {
         self.inner.value.borrow().clone()
     }
 }
@@ -45,13 +72,7 @@ impl<T> SetState<T> {
     pub fn set(&self, new_value: T) {
         *self.inner.value.borrow_mut() = new_value;
 
-        let subscribers: Vec<_> = self.inner.subscribers.borrow().iter().cloned().collect();
-
-        for id in subscribers {
-            if let Some(effect) = self.inner.ctx.effects.borrow().get(&id) {
-                effect();
-            }
-        }
+        self.notify();
     }
 
     pub fn update<F>(&self, mut update_fn: F)
@@ -63,52 +84,476 @@ impl<T> SetState<T> {
         drop(current); // important !!!! release immutable borrow
         *self.inner.value.borrow_mut() = new_value;
 
-        let subscribers: Vec<_> = self.inner.subscribers.borrow().iter().cloned().collect();
+        self.notify();
+    }
 
-        for id in subscribers {
-            if let Some(effect) = self.inner.ctx.effects.borrow().get(&id) {
-                effect();
-            }
+    // A handle for setting this state from other threads. Values are posted to
+    // the app's event loop and applied on the UI thread, so there is none
+    // outside of a running app.
+    pub fn sender(&self) -> Option<StateSender<T>>
+    where
+        T: Send + 'static,
+    {
+        let ctx = &self.inner.ctx;
+        let tx = ctx.event_sender.borrow().clone()?;
+
+        let id = match self.inner.remote_id.get() {
+            Some(id) => id,
+            None => {
+                let id = ctx.next_remote_id.get();
+                ctx.next_remote_id.set(id + 1);
+                self.inner.remote_id.set(Some(id));
+                id
+            },
+        };
+
+        if !ctx.remote_setters.borrow().contains_key(&id) {
+            // The context owns the setter, so it holds the state weakly and is
+            // handed the context when called rather than keeping a clone
+            let value = Rc::downgrade(&self.inner.value);
+            let subscribers = Rc::downgrade(&self.inner.subscribers);
+            let owner = self.inner.owner;
+            let remote_id = self.inner.remote_id.clone();
+
+            ctx.remote_setters.borrow_mut().insert(id, Box::new(move |ctx: &StateContext, update: Box<dyn Any + Send>| {
+                let (Some(value), Some(subscribers)) = (value.upgrade(), subscribers.upgrade()) else {
+                    return;
+                };
+
+                let setter = SetState {
+                    inner: InnerState { value, ctx: ctx.clone(), subscribers, owner, remote_id: remote_id.clone() },
+                };
+
+                if let Ok(update) = update.downcast::<RemoteUpdate<T>>() {
+                    match *update {
+                        RemoteUpdate::Set(value) => setter.set(value),
+                        RemoteUpdate::Update(update_fn) => {
+                            let value = update_fn(&setter.inner.value.borrow());
+                            setter.set(value);
+                        },
+                        RemoteUpdate::TryUpdate(update_fn) => {
+                            let value = update_fn(&setter.inner.value.borrow());
+
+                            if let Some(value) = value {
+                                setter.set(value);
+                            }
+                        },
+                    }
+                }
+            }));
+
+            ctx.register_remote_setter(self.inner.owner, id);
         }
+
+        Some(StateSender { id, tx, _marker: PhantomData })
+    }
+
+    fn notify(&self) {
+        let ctx = &self.inner.ctx;
+
+        ctx.batch(|| {
+            ctx.pending_effects.borrow_mut()
+                .extend(self.inner.subscribers.borrow().iter().copied());
+        });
+
+        ctx.mark_dirty();
+    }
+}
+
+pub type Reducer<S, A> = fn(&S, A) -> S;
+
+type Middleware<S, A> = Rc<dyn Fn(&S, &A)>;
+
+pub struct Dispatch<A> {
+    dispatch: Rc<dyn Fn(A)>,
+}
+
+impl<A> Clone for Dispatch<A> {
+    fn clone(&self) -> Self {
+        Dispatch { dispatch: self.dispatch.clone() }
+    }
+}
+
+impl<A> Dispatch<A> {
+    pub fn dispatch(&self, action: A) {
+        (self.dispatch)(action);
     }
 }
 
-pub fn use_state<T: Clone>(ctx: StateContext, initial: T) -> (GetState<T>, SetState<T>) {
-    let inner = InnerState {
-        value: Rc::new(RefCell::new(initial)),
-        ctx: ctx.clone(),
-        subscribers: Rc::new(RefCell::new(HashSet::new())),
+pub fn use_reducer<S, A>(ctx: StateContext, initial: S, reducer: Reducer<S, A>) -> (GetState<S>, Dispatch<A>)
+where
+    S: Clone + 'static,
+    A: 'static,
+{
+    use_reducer_with_middleware(ctx, initial, reducer, |_: &S, _: &A| {})
+}
+
+// Like use_reducer, with `middleware` seeing the current state and every
+// action before it is reduced, e.g. to log actions or keep an undo history.
+pub fn use_reducer_with_middleware<S, A, M>(ctx: StateContext, initial: S, reducer: Reducer<S, A>, middleware: M) -> (GetState<S>, Dispatch<A>)
+where
+    S: Clone + 'static,
+    A: 'static,
+    M: Fn(&S, &A) + 'static,
+{
+    let (state, set_state) = use_state(ctx.clone(), initial);
+
+    // The latest middleware is kept so it sees the captures of the last render
+    let current_middleware = ctx.use_hook(|| RefCell::new(None::<Middleware<S, A>>));
+    *current_middleware.borrow_mut() = Some(Rc::new(middleware));
+
+    let dispatch = Dispatch {
+        dispatch: Rc::new(move |action| {
+            let middleware = current_middleware.borrow().clone();
+            let value = set_state.inner.value.borrow().clone();
+
+            if let Some(middleware) = middleware {
+                middleware(&value, &action);
+            }
+
+            set_state.set(reducer(&value, action));
+        }),
     };
 
-    (GetState { inner: inner.clone() }, SetState { inner: inner.clone() })
+    (state, dispatch)
 }
 
-pub fn use_effect<F: Fn() + 'static>(ctx: StateContext, f: F) {
-    let id = ctx.next_effect_id.get();
+// Returns the value of type T from the nearest enclosing Provider. Providing a
+// GetState makes consumers reading it update with the state.
+pub fn use_context<T: Clone + 'static>(ctx: StateContext) -> Option<T> {
+    ctx.contexts.borrow().iter()
+        .rev()
+        .find_map(|value| value.downcast_ref::<T>())
+        .cloned()
+}
 
-    ctx.next_effect_id.set(id + 1);
+// Runs `f` with effects deferred until the outermost batch finishes, so each
+// subscribed effect runs once no matter how many of its states were set.
+pub fn batch<F: FnOnce() -> R, R>(ctx: StateContext, f: F) -> R {
+    ctx.batch(f)
+}
+
+// Shuts the app down once the current event has been handled. It can be sent
+// to other threads.
+#[derive(Clone)]
+pub struct Exit {
+    tx: Option<Sender<Event>>,
+}
+
+impl Exit {
+    pub fn exit(&self) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(Event::Quit);
+        }
+    }
+}
+
+pub fn use_exit(ctx: StateContext) -> Exit {
+    Exit {
+        tx: ctx.event_sender.borrow().clone(),
+    }
+}
+
+impl<T> InnerState<T> {
+    fn new(ctx: &StateContext, initial: T) -> Self {
+        InnerState {
+            value: Rc::new(RefCell::new(initial)),
+            ctx: ctx.clone(),
+            subscribers: Rc::new(RefCell::new(HashSet::new())),
+            owner: ctx.current_scope(),
+            remote_id: Rc::new(Cell::new(None)),
+        }
+    }
+}
 
-    let f = Rc::new(f);
-    ctx.effects.borrow_mut().insert(id, f.clone());
+type TryUpdateFn<T> = Box<dyn FnOnce(&T) -> Option<T> + Send>;
 
-    let wrapped = {
+enum RemoteUpdate<T> {
+    Set(T),
+    Update(Box<dyn FnOnce(&T) -> T + Send>),
+    TryUpdate(TryUpdateFn<T>),
+}
+
+// A state change posted from another thread, see SetState::sender. Clones
+// share the update, which is applied by whichever is handled first.
+#[derive(Clone)]
+pub struct StateUpdate {
+    id: usize,
+    update: Arc<Mutex<Option<Box<dyn Any + Send>>>>,
+}
+
+impl fmt::Debug for StateUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateUpdate")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct StateSender<T> {
+    id: usize,
+    tx: Sender<Event>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for StateSender<T> {
+    fn clone(&self) -> Self {
+        StateSender { id: self.id, tx: self.tx.clone(), _marker: PhantomData }
+    }
+}
+
+// Sending fails only once the app has shut down, at which point there is
+// nothing left to update.
+impl<T: Send + 'static> StateSender<T> {
+    pub fn set(&self, new_value: T) {
+        self.send(RemoteUpdate::Set(new_value));
+    }
+
+    pub fn update<F>(&self, update_fn: F)
+    where
+        F: FnOnce(&T) -> T + Send + 'static,
+    {
+        self.send(RemoteUpdate::Update(Box::new(update_fn)));
+    }
+
+    // Like update, leaving the state as it is, without notifying anyone, when
+    // `update_fn` returns None.
+    pub(crate) fn try_update<F>(&self, update_fn: F)
+    where
+        F: FnOnce(&T) -> Option<T> + Send + 'static,
+    {
+        self.send(RemoteUpdate::TryUpdate(Box::new(update_fn)));
+    }
+
+    fn send(&self, update: RemoteUpdate<T>) {
+        let _ = self.tx.send(Event::State(StateUpdate {
+            id: self.id,
+            update: Arc::new(Mutex::new(Some(Box::new(update)))),
+        }));
+    }
+}
+
+pub fn use_state<T: Clone + 'static>(ctx: StateContext, initial: T) -> (GetState<T>, SetState<T>) {
+    let inner = ctx.use_hook(|| InnerState::new(&ctx, initial));
+
+    (GetState { inner: (*inner).clone() }, SetState { inner: (*inner).clone() })
+}
+
+// A value derived from other states. It is computed once up front and again
+// only when one of the states read by `f` is set, and can itself be read by
+// effects and other memos. Those are only notified when the recomputed value
+// differs from the previous one.
+//
+// Like use_effect, later renders only swap in the new `f`. Anything else it
+// captures, like a prop, is used as it was when the memo last recomputed, so
+// keep such inputs in state if the memo should follow them.
+pub fn use_memo<T, F>(ctx: StateContext, f: F) -> GetState<T>
+where
+    T: Clone + PartialEq + 'static,
+    F: Fn() -> T + 'static,
+{
+    let memo = ctx.use_hook(|| RefCell::new(None::<InnerState<T>>));
+
+    use_effect(ctx.clone(), {
         let ctx = ctx.clone();
+        let memo = memo.clone();
 
         move || {
-            *ctx.current_effect.borrow_mut() = Some(id);
-            f();
-            *ctx.current_effect.borrow_mut() = None;
+            let value = f();
+            let inner = memo.borrow().clone();
+
+            match inner {
+                Some(inner) if *inner.value.borrow() == value => {},
+                Some(inner) => SetState { inner }.set(value),
+                None => *memo.borrow_mut() = Some(InnerState::new(&ctx, value)),
+            }
         }
-    };
+    });
+
+    let inner = memo.borrow().clone()
+        .expect("memo is computed when first used");
+
+    GetState { inner }
+}
+
+pub trait IntoEffectCleanup {
+    fn into_effect_cleanup(self) -> Option<Cleanup>;
+}
+
+impl IntoEffectCleanup for () {
+    fn into_effect_cleanup(self) -> Option<Cleanup> {
+        None
+    }
+}
+
+impl<F: FnOnce() + 'static> IntoEffectCleanup for F {
+    fn into_effect_cleanup(self) -> Option<Cleanup> {
+        Some(Box::new(self))
+    }
+}
+
+impl<F: FnOnce() + 'static> IntoEffectCleanup for Option<F> {
+    fn into_effect_cleanup(self) -> Option<Cleanup> {
+        self.map(|f| Box::new(f) as Cleanup)
+    }
+}
+
+// The effect runs immediately and again whenever state it read changes. A
+// closure returned from it is called before the next run and when the owning
+// component is unmounted.
+pub fn use_effect<F, R>(ctx: StateContext, f: F)
+where
+    F: Fn() -> R + 'static,
+    R: IntoEffectCleanup,
+{
+    let f: EffectFn = Rc::new(move || f().into_effect_cleanup());
+    let mut first_run = false;
+
+    let id = *ctx.use_hook(|| {
+        first_run = true;
+
+        let id = ctx.next_effect_id.get();
+        ctx.next_effect_id.set(id + 1);
+        ctx.register_effect(id);
+        id
+    });
+
+    // On later renders only the closure is swapped, so it sees the latest
+    // captures without re-running.
+    let mut effects = ctx.effects.borrow_mut();
+
+    match effects.get_mut(&id) {
+        Some(effect) => effect.f = f,
+        None => {
+            effects.insert(id, Effect {
+                f,
+                cleanup: None,
+                dependencies: vec![],
+            });
+        },
+    }
+
+    drop(effects);
+
+    if first_run {
+        ctx.run_effect(id);
+    }
+}
+
+pub type Cleanup = Box<dyn FnOnce()>;
+
+type EffectFn = Rc<dyn Fn() -> Option<Cleanup>>;
+
+type RemoteSetter = Box<dyn Fn(&StateContext, Box<dyn Any + Send>)>;
 
-    wrapped(); // Run immediately
+pub(crate) type Callback = Rc<RefCell<Rc<dyn Fn()>>>;
+
+struct Timer {
+    due: Instant,
+    interval: Option<Duration>,
+    callback: Callback,
+}
+
+struct Effect {
+    f: EffectFn,
+    cleanup: Option<Cleanup>,
+    dependencies: Vec<Weak<RefCell<HashSet<usize>>>>,
+}
+
+fn unsubscribe(id: usize, dependencies: &[Weak<RefCell<HashSet<usize>>>]) {
+    for subscribers in dependencies.iter().filter_map(Weak::upgrade) {
+        subscribers.borrow_mut().remove(&id);
+    }
+}
+
+pub type ScopeId = usize;
+
+const ROOT_SCOPE: ScopeId = 0;
+
+// Identifies a child component between renders of its parent: either by its
+// type and position among the parent's unkeyed children, or by an explicit key.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum ChildKey {
+    Position(&'static str, usize),
+    Keyed(&'static str, Rc<str>),
+}
+
+// Hook storage for a single component instance. Hooks claim slots in the order
+// they are called, so they survive the component function being run again.
+#[derive(Default)]
+struct Scope {
+    hooks: Vec<Rc<dyn Any>>,
+    hook_cursor: usize,
+    effects: Vec<usize>,
+    remote_setters: Vec<usize>,
+
+    // Children claimed during the current render, and the ones left over from
+    // the previous render that have not been matched yet.
+    children: Vec<(ChildKey, ScopeId)>,
+    previous_children: HashMap<ChildKey, ScopeId>,
+    position: usize,
+
+    focus_cursor: usize,
+}
+
+// Keeps a provided value in place until dropped. The default guard provides
+// nothing.
+#[derive(Default)]
+pub struct ContextGuard {
+    ctx: Option<StateContext>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        if let Some(ctx) = &self.ctx {
+            ctx.contexts.borrow_mut().pop();
+        }
+    }
+}
+
+pub struct ScopeGuard {
+    ctx: StateContext,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        self.ctx.exit_scope();
+    }
 }
 
 #[derive(Clone, Default)]
 pub struct StateContext {
     current_effect: Rc<RefCell<Option<usize>>>,
-    effects: Rc<RefCell<HashMap<usize, Rc<dyn Fn()>>>>,
+    effects: Rc<RefCell<HashMap<usize, Effect>>>,
     next_effect_id: Rc<Cell<usize>>,
+
+    scopes: Rc<RefCell<HashMap<ScopeId, Scope>>>,
+    scope_stack: Rc<RefCell<Vec<ScopeId>>>,
+    next_scope_id: Rc<Cell<ScopeId>>,
+    dirty: Rc<Cell<bool>>,
+
+    batch_depth: Rc<Cell<usize>>,
+    // Ordered by effect id, so effects run in the order they were created
+    pending_effects: Rc<RefCell<BTreeSet<usize>>>,
+
+    contexts: Rc<RefCell<Vec<Rc<dyn Any>>>>,
+
+    event_sender: Rc<RefCell<Option<Sender<Event>>>>,
+    remote_setters: Rc<RefCell<HashMap<usize, RemoteSetter>>>,
+    next_remote_id: Rc<Cell<usize>>,
+
+    timers: Rc<RefCell<HashMap<usize, Timer>>>,
+    next_timer_id: Rc<Cell<usize>>,
+    clock_offset: Rc<Cell<Duration>>,
+
+    actions: Rc<RefCell<HashMap<usize, (&'static str, Callback)>>>,
+    next_action_id: Rc<Cell<usize>>,
+
+    focus: Rc<RefCell<FocusManager>>,
+    terminal_focus: Rc<RefCell<bool>>,
+    terminal_focus_subscribers: Rc<RefCell<HashSet<usize>>>,
+
+    key: Option<Rc<str>>,
 }
 
 impl StateContext {
@@ -117,6 +562,1020 @@ impl StateContext {
             current_effect: Rc::new(RefCell::new(None)),
             effects: Rc::new(RefCell::new(HashMap::new())),
             next_effect_id: Rc::new(Cell::new(0)),
+
+            scopes: Rc::new(RefCell::new(HashMap::new())),
+            scope_stack: Rc::new(RefCell::new(vec![])),
+            next_scope_id: Rc::new(Cell::new(ROOT_SCOPE + 1)),
+            dirty: Rc::new(Cell::new(false)),
+
+            batch_depth: Rc::new(Cell::new(0)),
+            pending_effects: Rc::new(RefCell::new(BTreeSet::new())),
+
+            contexts: Rc::new(RefCell::new(vec![])),
+
+            event_sender: Rc::new(RefCell::new(None)),
+            remote_setters: Rc::new(RefCell::new(HashMap::new())),
+            next_remote_id: Rc::new(Cell::new(0)),
+
+            timers: Rc::new(RefCell::new(HashMap::new())),
+            next_timer_id: Rc::new(Cell::new(0)),
+            clock_offset: Rc::new(Cell::new(Duration::ZERO)),
+
+            actions: Rc::new(RefCell::new(HashMap::new())),
+            next_action_id: Rc::new(Cell::new(0)),
+
+            focus: Rc::new(RefCell::new(FocusManager::default())),
+            terminal_focus: Rc::new(RefCell::new(true)),
+            terminal_focus_subscribers: Rc::new(RefCell::new(HashSet::new())),
+
+            key: None,
+        }
+    }
+
+    // The context handed to a component created with `key={...}` in ui!.
+    pub fn keyed(&self, key: impl ToString) -> StateContext {
+        StateContext {
+            key: Some(key.to_string().into()),
+            ..self.clone()
+        }
+    }
+
+    pub fn unkeyed(&self) -> StateContext {
+        StateContext {
+            key: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn batch<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.batch_depth.set(self.batch_depth.get() + 1);
+
+        let result = f();
+
+        // Effects set state of their own while flushing, which is queued and
+        // picked up by this same loop as the depth is still above zero. The
+        // oldest effect goes first, so a memo recomputes before the effects
+        // created after it that read it.
+        if self.batch_depth.get() == 1 {
+            loop {
+                let next = self.pending_effects.borrow_mut().pop_first();
+
+                match next {
+                    Some(id) => self.run_effect(id),
+                    None => break,
+                }
+            }
+        }
+
+        self.batch_depth.set(self.batch_depth.get() - 1);
+
+        result
+    }
+
+    // Makes `value` available to use_context for everything rendered until the
+    // guard is dropped, which is what a Provider's `value` takes.
+    pub fn provide<T: 'static>(&self, value: T) -> ContextGuard {
+        self.contexts.borrow_mut().push(Rc::new(value));
+
+        ContextGuard { ctx: Some(self.clone()) }
+    }
+
+    // Enters the scope of the component of type `kind` being rendered. Outside
+    // of any scope this is the root scope, otherwise the previous instance
+    // matching this context's key, or type and position, under the current
+    // scope is reused. The scope is left when the guard is dropped.
+    pub fn scope(&self, kind: &'static str) -> ScopeGuard {
+        let parent = self.current_scope();
+        let mut scopes = self.scopes.borrow_mut();
+
+        let id = match parent {
+            None => ROOT_SCOPE,
+            Some(parent) => {
+                let parent = scopes.entry(parent).or_default();
+
+                let key = match &self.key {
+                    Some(key) => ChildKey::Keyed(kind, key.clone()),
+                    None => {
+                        parent.position += 1;
+                        ChildKey::Position(kind, parent.position - 1)
+                    }
+                };
+
+                let id = parent.previous_children.remove(&key)
+                    .unwrap_or_else(|| {
+                        let id = self.next_scope_id.get();
+                        self.next_scope_id.set(id + 1);
+                        id
+                    });
+
+                parent.children.push((key, id));
+                id
+            }
+        };
+
+        let scope = scopes.entry(id).or_default();
+        scope.hook_cursor = 0;
+        scope.position = 0;
+        scope.focus_cursor = 0;
+
+        let mut duplicates = vec![];
+
+        for (key, child) in scope.children.drain(..) {
+            if let Some(duplicate) = scope.previous_children.insert(key, child) {
+                duplicates.push(duplicate);
+            }
+        }
+
+        drop(scopes);
+
+        for duplicate in duplicates {
+            self.dispose_scope(duplicate);
+        }
+
+        self.scope_stack.borrow_mut().push(id);
+
+        ScopeGuard { ctx: self.clone() }
+    }
+
+    // For built-in components, which have no scope of their own: enters one
+    // only when created with a key, so what they claim in it, like a focus
+    // id, follows the key instead of their position.
+    pub fn keyed_scope(&self, kind: &'static str) -> Option<ScopeGuard> {
+        self.key.is_some().then(|| self.scope(kind))
+    }
+
+    pub fn current_scope(&self) -> Option<ScopeId> {
+        self.scope_stack.borrow().last().copied()
+    }
+
+    // Returns whether any state was set since the last call. The app then
+    // rebuilds the whole tree from the root, there is no tracking of which
+    // components read the state.
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.replace(false)
+    }
+
+    fn mark_dirty(&self) {
+        self.dirty.set(true);
+    }
+
+    fn exit_scope(&self) {
+        let Some(id) = self.scope_stack.borrow_mut().pop() else {
+            return;
+        };
+
+        let unmatched: Vec<_> = match self.scopes.borrow_mut().get_mut(&id) {
+            Some(scope) => scope.previous_children.drain().map(|(_, child)| child).collect(),
+            None => vec![],
+        };
+
+        for child in unmatched {
+            self.dispose_scope(child);
+        }
+    }
+
+    // Drops the hook storage of a component that was not rendered again, along
+    // with everything below it.
+    fn dispose_scope(&self, id: ScopeId) {
+        let scope = self.scopes.borrow_mut().remove(&id);
+
+        let Some(scope) = scope else {
+            return;
+        };
+
+        let children = scope.children.iter()
+            .map(|(_, child)| *child)
+            .chain(scope.previous_children.values().copied());
+
+        for child in children {
+            self.dispose_scope(child);
+        }
+
+        for effect in scope.effects {
+            self.dispose_effect(effect);
+        }
+
+        let mut remote_setters = self.remote_setters.borrow_mut();
+
+        for id in &scope.remote_setters {
+            remote_setters.remove(id);
+        }
+    }
+
+    // Returns the value stored in the current scope's next hook slot, creating
+    // it with `init` on the first render. Outside of a scope nothing is kept.
+    pub(crate) fn use_hook<T: 'static, F: FnOnce() -> T>(&self, init: F) -> Rc<T> {
+        let Some(id) = self.current_scope() else {
+            return Rc::new(init());
+        };
+
+        let existing = {
+            let mut scopes = self.scopes.borrow_mut();
+            let scope = scopes.entry(id).or_default();
+            let slot = scope.hook_cursor;
+            scope.hook_cursor += 1;
+
+            scope.hooks.get(slot).cloned()
+        };
+
+        if let Some(hook) = existing {
+            return hook.downcast::<T>()
+                .expect("hooks must be called in the same order on every render");
+        }
+
+        // init must not call other hooks, their slots would be taken out of order
+        let hook = Rc::new(init());
+
+        if let Some(scope) = self.scopes.borrow_mut().get_mut(&id) {
+            scope.hooks.push(hook.clone());
+        }
+
+        hook
+    }
+
+    // Runs an effect, dropping the dependencies of its previous run first so
+    // it only stays subscribed to the states it reads this time.
+    fn run_effect(&self, id: usize) {
+        let (f, cleanup, dependencies) = match self.effects.borrow_mut().get_mut(&id) {
+            Some(effect) => (
+                effect.f.clone(),
+                effect.cleanup.take(),
+                std::mem::take(&mut effect.dependencies),
+            ),
+            None => return,
+        };
+
+        unsubscribe(id, &dependencies);
+
+        if let Some(cleanup) = cleanup {
+            cleanup();
+        }
+
+        let previous = self.current_effect.replace(Some(id));
+        let cleanup = f();
+        *self.current_effect.borrow_mut() = previous;
+
+        let cleanup = match self.effects.borrow_mut().get_mut(&id) {
+            Some(effect) => {
+                effect.cleanup = cleanup;
+                None
+            },
+            None => cleanup,
+        };
+
+        // the effect was disposed while it ran
+        if let Some(cleanup) = cleanup {
+            cleanup();
+        }
+    }
+
+    fn dispose_effect(&self, id: usize) {
+        let Some(effect) = self.effects.borrow_mut().remove(&id) else {
+            return;
+        };
+
+        unsubscribe(id, &effect.dependencies);
+
+        if let Some(cleanup) = effect.cleanup {
+            cleanup();
+        }
+    }
+
+    // Subscribes the effect currently running to a state being read.
+    fn track(&self, subscribers: &Rc<RefCell<HashSet<usize>>>) {
+        let Some(id) = *self.current_effect.borrow() else {
+            return;
+        };
+
+        if subscribers.borrow_mut().insert(id)
+            && let Some(effect) = self.effects.borrow_mut().get_mut(&id)
+        {
+            effect.dependencies.push(Rc::downgrade(subscribers));
+        }
+    }
+
+    // Unmounts every component, running the cleanup of all their effects.
+    pub fn dispose(&self) {
+        self.dispose_scope(ROOT_SCOPE);
+    }
+
+    // Claims the id of a focusable component created in the current scope.
+    pub fn focus_id(&self) -> FocusId {
+        let scope = self.current_scope().unwrap_or(ROOT_SCOPE);
+        let mut scopes = self.scopes.borrow_mut();
+        let state = scopes.entry(scope).or_default();
+
+        state.focus_cursor += 1;
+
+        FocusId { scope, index: state.focus_cursor - 1 }
+    }
+
+    pub fn focus_manager(&self) -> &Rc<RefCell<FocusManager>> {
+        &self.focus
+    }
+
+    pub fn is_focused(&self, id: FocusId) -> bool {
+        self.focus.borrow().focused() == Some(id)
+    }
+
+    pub fn focus(&self, id: FocusId) {
+        self.focus.borrow_mut().focus(Some(id));
+    }
+
+    // Whether the terminal window has focus. It isn't owned by any component,
+    // so setting it re-renders from the root.
+    pub(crate) fn terminal_focus(&self) -> GetState<bool> {
+        GetState { inner: self.terminal_focus_state() }
+    }
+
+    pub(crate) fn set_terminal_focus(&self, focused: bool) {
+        let inner = self.terminal_focus_state();
+
+        if *inner.value.borrow() != focused {
+            SetState { inner }.set(focused);
+        }
+    }
+
+    fn terminal_focus_state(&self) -> InnerState<bool> {
+        InnerState {
+            value: self.terminal_focus.clone(),
+            ctx: self.clone(),
+            subscribers: self.terminal_focus_subscribers.clone(),
+            owner: None,
+            remote_id: Rc::new(Cell::new(None)),
+        }
+    }
+
+    // The time timers are scheduled against. It only differs from the system
+    // clock once advance_clock has been used, as the test harness does.
+    pub fn now(&self) -> Instant {
+        Instant::now() + self.clock_offset.get()
+    }
+
+    pub(crate) fn clock_offset(&self) -> Duration {
+        self.clock_offset.get()
+    }
+
+    pub(crate) fn advance_clock(&self, duration: Duration) {
+        self.clock_offset.set(self.clock_offset.get() + duration);
+    }
+
+    pub(crate) fn add_timer(&self, delay: Duration, interval: Option<Duration>, callback: Callback) -> usize {
+        let id = self.next_timer_id.get();
+        self.next_timer_id.set(id + 1);
+
+        self.timers.borrow_mut().insert(id, Timer {
+            due: self.now() + delay,
+            interval,
+            callback,
+        });
+
+        id
+    }
+
+    pub(crate) fn remove_timer(&self, id: usize) {
+        self.timers.borrow_mut().remove(&id);
+    }
+
+    // Fires every timer that is due, called on each tick of the event loop.
+    // Intervals that fell behind fire once and are rescheduled from now
+    // instead of catching up.
+    pub(crate) fn run_timers(&self) {
+        let now = self.now();
+
+        let mut due: Vec<_> = self.timers.borrow().iter()
+            .filter(|(_, timer)| timer.due <= now)
+            .map(|(id, timer)| (timer.due, *id))
+            .collect();
+
+        due.sort();
+
+        for (_, id) in due {
+            let callback = {
+                let mut timers = self.timers.borrow_mut();
+
+                let Some(timer) = timers.get_mut(&id) else {
+                    continue;
+                };
+
+                let callback = timer.callback.borrow().clone();
+
+                match timer.interval {
+                    Some(interval) => timer.due = (timer.due + interval).max(now),
+                    None => {
+                        timers.remove(&id);
+                    },
+                }
+
+                callback
+            };
+
+            callback();
+        }
+    }
+
+    pub(crate) fn add_action(&self, name: &'static str, callback: Callback) -> usize {
+        let id = self.next_action_id.get();
+        self.next_action_id.set(id + 1);
+
+        self.actions.borrow_mut().insert(id, (name, callback));
+        id
+    }
+
+    pub(crate) fn remove_action(&self, id: usize) {
+        self.actions.borrow_mut().remove(&id);
+    }
+
+    // Calls every handler registered for the action, in the order they were
+    // added.
+    pub(crate) fn run_action(&self, name: &str) {
+        let mut handlers: Vec<_> = self.actions.borrow().iter()
+            .filter(|(_, (action, _))| *action == name)
+            .map(|(id, (_, callback))| (*id, callback.borrow().clone()))
+            .collect();
+
+        handlers.sort_by_key(|(id, _)| *id);
+
+        for (_, handler) in handlers {
+            handler();
+        }
+    }
+
+    pub(crate) fn set_event_sender(&self, tx: Sender<Event>) {
+        *self.event_sender.borrow_mut() = Some(tx);
+    }
+
+    pub(crate) fn apply_update(&self, update: StateUpdate) {
+        let Some(value) = update.update.lock().ok().and_then(|mut value| value.take()) else {
+            return;
+        };
+
+        let setter = self.remote_setters.borrow_mut().remove(&update.id);
+
+        // Taken out while it runs since setting state can register new setters
+        if let Some(setter) = setter {
+            setter(self, value);
+            self.remote_setters.borrow_mut().entry(update.id).or_insert(setter);
+        }
+    }
+
+    fn register_remote_setter(&self, owner: Option<ScopeId>, id: usize) {
+        if let Some(scope) = owner
+            && let Some(scope) = self.scopes.borrow_mut().get_mut(&scope)
+        {
+            scope.remote_setters.push(id);
+        }
+    }
+
+    fn register_effect(&self, id: usize) {
+        if let Some(scope) = self.current_scope()
+            && let Some(scope) = self.scopes.borrow_mut().get_mut(&scope)
+        {
+            scope.effects.push(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setting_state_marks_the_context_dirty_once() {
+        let ctx = StateContext::new();
+        let (_, set_count) = use_state(ctx.clone(), 0);
+
+        assert!(!ctx.take_dirty());
+
+        set_count.set(1);
+        set_count.set(2);
+
+        assert!(ctx.take_dirty());
+        assert!(!ctx.take_dirty());
+    }
+
+    // Renders a parent with one child scope per entry, returning the ids the
+    // children were given.
+    fn render(ctx: &StateContext, children: &[(&'static str, Option<&str>)]) -> Vec<ScopeId> {
+        let _root = ctx.scope("root");
+
+        children.iter()
+            .map(|(kind, key)| {
+                let ctx = match key {
+                    Some(key) => ctx.keyed(key),
+                    None => ctx.unkeyed(),
+                };
+
+                let _child = ctx.scope(kind);
+                ctx.current_scope().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scopes_are_matched_by_type_and_position() {
+        let ctx = StateContext::new();
+
+        let first = render(&ctx, &[("a", None), ("b", None)]);
+        let second = render(&ctx, &[("a", None), ("b", None)]);
+        assert_eq!(first, second);
+
+        // b moved to a position last held by an a
+        let third = render(&ctx, &[("b", None), ("a", None)]);
+        assert_ne!(third[0], first[1]);
+        assert_ne!(third[1], first[0]);
+    }
+
+    #[test]
+    fn keyed_scopes_follow_their_key() {
+        let ctx = StateContext::new();
+
+        let first = render(&ctx, &[("a", Some("x")), ("a", Some("y"))]);
+        let second = render(&ctx, &[("a", Some("y")), ("a", Some("x"))]);
+
+        assert_eq!(first, vec![second[1], second[0]]);
+    }
+
+    #[test]
+    fn duplicate_keys_get_separate_scopes() {
+        let ctx = StateContext::new();
+
+        let first = render(&ctx, &[("a", Some("x")), ("a", Some("x"))]);
+        assert_ne!(first[0], first[1]);
+
+        let second = render(&ctx, &[("a", Some("x")), ("a", Some("x"))]);
+        assert_ne!(second[0], second[1]);
+    }
+
+    #[test]
+    fn scopes_not_rendered_again_are_disposed() {
+        let ctx = StateContext::new();
+        let cleaned_up = Rc::new(Cell::new(false));
+
+        {
+            let _root = ctx.scope("root");
+            let child = ctx.unkeyed();
+            let _child = child.scope("a");
+
+            let cleaned_up = cleaned_up.clone();
+            use_effect(child.clone(), move || {
+                let cleaned_up = cleaned_up.clone();
+                move || cleaned_up.set(true)
+            });
+        }
+
+        assert!(!cleaned_up.get());
+
+        render(&ctx, &[]);
+        assert!(cleaned_up.get());
+        assert_eq!(ctx.scopes.borrow().len(), 1);
+    }
+
+    #[test]
+    fn hooks_keep_their_slot_between_renders() {
+        let ctx = StateContext::new();
+
+        let first = {
+            let _root = ctx.scope("root");
+            use_state(ctx.clone(), 1).1.set(2);
+            use_state(ctx.clone(), "a").0.get()
+        };
+
+        let (count, label) = {
+            let _root = ctx.scope("root");
+            (use_state(ctx.clone(), 1).0.get(), use_state(ctx.clone(), "b").0.get())
+        };
+
+        assert_eq!(first, "a");
+        assert_eq!((count, label), (2, "a"));
+    }
+
+    #[test]
+    fn keyed_scope_is_only_entered_with_a_key() {
+        let ctx = StateContext::new();
+        let _root = ctx.scope("root");
+
+        assert!(ctx.unkeyed().keyed_scope("Stack").is_none());
+        assert_eq!(ctx.current_scope(), Some(ROOT_SCOPE));
+
+        let keyed = ctx.keyed("x");
+        let guard = keyed.keyed_scope("Stack");
+        assert!(guard.is_some());
+        assert_ne!(ctx.current_scope(), Some(ROOT_SCOPE));
+    }
+
+    // A shared log for effects to write to, and a function taking what they
+    // wrote since it was last called.
+    fn log() -> (Rc<RefCell<Vec<String>>>, impl Fn() -> Vec<String>) {
+        let log = Rc::new(RefCell::new(vec![]));
+
+        let take = {
+            let log = log.clone();
+            move || std::mem::take(&mut *log.borrow_mut())
+        };
+
+        (log, take)
+    }
+
+    #[test]
+    fn cleanup_runs_before_the_effect_runs_again() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+        let (count, set_count) = use_state(ctx.clone(), 0);
+
+        use_effect(ctx.clone(), move || {
+            let value = count.get();
+            log.borrow_mut().push(format!("run {value}"));
+
+            let log = log.clone();
+            move || log.borrow_mut().push(format!("cleanup {value}"))
+        });
+
+        assert_eq!(take(), ["run 0"]);
+
+        set_count.set(1);
+        assert_eq!(take(), ["cleanup 0", "run 1"]);
+    }
+
+    #[test]
+    fn cleanup_runs_when_the_component_is_unmounted() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+
+        {
+            let _root = ctx.scope("root");
+            let child = ctx.unkeyed();
+            let _child = child.scope("child");
+
+            use_effect(child.clone(), move || {
+                let log = log.clone();
+                move || log.borrow_mut().push("cleanup".to_string())
+            });
+        }
+
+        assert!(take().is_empty());
+
+        ctx.dispose();
+        assert_eq!(take(), ["cleanup"]);
+    }
+
+    #[test]
+    fn effects_run_once_per_mount() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+
+        for _ in 0..3 {
+            let _root = ctx.scope("root");
+            let log = log.clone();
+            use_effect(ctx.clone(), move || log.borrow_mut().push("run".to_string()));
         }
+
+        assert_eq!(take(), ["run"]);
+    }
+
+    #[test]
+    fn effects_only_follow_the_states_read_on_their_last_run() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+
+        let (use_a, set_use_a) = use_state(ctx.clone(), true);
+        let (a, set_a) = use_state(ctx.clone(), 0);
+        let (b, set_b) = use_state(ctx.clone(), 0);
+
+        use_effect(ctx.clone(), move || {
+            let entry = match use_a.get() {
+                true => format!("a {}", a.get()),
+                false => format!("b {}", b.get()),
+            };
+
+            log.borrow_mut().push(entry);
+        });
+
+        assert_eq!(take(), ["a 0"]);
+
+        set_b.set(1);
+        assert!(take().is_empty());
+
+        set_use_a.set(false);
+        assert_eq!(take(), ["b 1"]);
+
+        set_a.set(1);
+        assert!(take().is_empty());
+
+        set_b.set(2);
+        assert_eq!(take(), ["b 2"]);
+    }
+
+    #[test]
+    fn untracked_reads_do_not_subscribe() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+        let (count, set_count) = use_state(ctx.clone(), 0);
+
+        use_effect(ctx.clone(), move || log.borrow_mut().push(count.get_untracked().to_string()));
+        assert_eq!(take(), ["0"]);
+
+        set_count.set(1);
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn batch_runs_each_effect_once_at_the_end() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+
+        let (a, set_a) = use_state(ctx.clone(), 0);
+        let (b, set_b) = use_state(ctx.clone(), 0);
+
+        use_effect(ctx.clone(), move || log.borrow_mut().push(format!("{} {}", a.get(), b.get())));
+        assert_eq!(take(), ["0 0"]);
+
+        batch(ctx.clone(), || {
+            set_a.set(1);
+            set_b.set(1);
+            set_a.set(2);
+        });
+
+        assert_eq!(take(), ["2 1"]);
+    }
+
+    #[test]
+    fn nested_batches_flush_with_the_outermost() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+        let (count, set_count) = use_state(ctx.clone(), 0);
+
+        use_effect(ctx.clone(), move || log.borrow_mut().push(count.get().to_string()));
+        take();
+
+        batch(ctx.clone(), || {
+            batch(ctx.clone(), || set_count.set(1));
+            assert!(take().is_empty());
+
+            set_count.set(2);
+        });
+
+        assert_eq!(take(), ["2"]);
+    }
+
+    #[test]
+    fn state_set_by_effects_during_a_flush_is_picked_up() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+
+        let (count, set_count) = use_state(ctx.clone(), 0);
+        let (double, set_double) = use_state(ctx.clone(), 0);
+
+        use_effect(ctx.clone(), move || set_double.set(count.get() * 2));
+        use_effect(ctx.clone(), move || log.borrow_mut().push(double.get().to_string()));
+        take();
+
+        batch(ctx.clone(), || set_count.set(2));
+        assert_eq!(take(), ["4"]);
+    }
+
+    #[test]
+    fn batch_returns_the_closures_result() {
+        let ctx = StateContext::new();
+        assert_eq!(batch(ctx, || 5), 5);
+    }
+
+    #[test]
+    fn memos_recompute_when_their_states_change() {
+        let ctx = StateContext::new();
+        let (count, set_count) = use_state(ctx.clone(), 1);
+
+        let double = use_memo(ctx.clone(), move || count.get() * 2);
+        assert_eq!(double.get(), 2);
+
+        set_count.set(4);
+        assert_eq!(double.get(), 8);
+    }
+
+    #[test]
+    fn memos_only_notify_when_their_value_changes() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+        let (count, set_count) = use_state(ctx.clone(), 1);
+
+        let is_even = use_memo(ctx.clone(), move || count.get() % 2 == 0);
+        use_effect(ctx.clone(), move || log.borrow_mut().push(is_even.get().to_string()));
+        assert_eq!(take(), ["false"]);
+
+        set_count.set(3);
+        assert!(take().is_empty());
+
+        set_count.set(4);
+        assert_eq!(take(), ["true"]);
+    }
+
+    #[test]
+    fn effects_reading_a_memo_and_its_source_run_once_after_the_memo() {
+        let ctx = StateContext::new();
+        let (log, take) = log();
+        let (count, set_count) = use_state(ctx.clone(), 1);
+
+        let doubled = use_memo(ctx.clone(), {
+            let count = count.clone();
+            move || count.get() * 2
+        });
+
+        use_effect(ctx.clone(), move || log.borrow_mut().push(format!("{} {}", count.get(), doubled.get())));
+        assert_eq!(take(), ["1 2"]);
+
+        set_count.set(2);
+        assert_eq!(take(), ["2 4"]);
+    }
+
+    #[test]
+    fn memos_keep_captured_values_until_a_state_they_read_changes() {
+        let ctx = StateContext::new();
+        let (count, set_count) = use_state(ctx.clone(), 1);
+
+        let render = |offset: i32| {
+            let _root = ctx.scope("root");
+            let count = count.clone();
+            use_memo(ctx.clone(), move || count.get() + offset)
+        };
+
+        assert_eq!(render(10).get(), 11);
+
+        // Only the closure is swapped, so the new offset isn't seen yet
+        let sum = render(20);
+        assert_eq!(sum.get(), 11);
+
+        set_count.set(2);
+        assert_eq!(sum.get(), 22);
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum CountAction {
+        Add(i64),
+        Reset,
+    }
+
+    fn count_reducer(count: &i64, action: CountAction) -> i64 {
+        match action {
+            CountAction::Add(amount) => count + amount,
+            CountAction::Reset => 0,
+        }
+    }
+
+    #[test]
+    fn dispatching_reduces_the_state() {
+        let ctx = StateContext::new();
+        let (count, dispatch) = use_reducer(ctx.clone(), 0, count_reducer);
+
+        dispatch.dispatch(CountAction::Add(2));
+        dispatch.dispatch(CountAction::Add(3));
+        assert_eq!(count.get(), 5);
+
+        dispatch.dispatch(CountAction::Reset);
+        assert_eq!(count.get(), 0);
+    }
+
+    #[test]
+    fn middleware_sees_the_state_before_each_action() {
+        let ctx = StateContext::new();
+        let seen = Rc::new(RefCell::new(vec![]));
+
+        let (count, dispatch) = use_reducer_with_middleware(ctx.clone(), 0, count_reducer, {
+            let seen = seen.clone();
+            move |count: &i64, action: &CountAction| seen.borrow_mut().push((*count, action.clone()))
+        });
+
+        dispatch.dispatch(CountAction::Add(2));
+        dispatch.dispatch(CountAction::Reset);
+
+        assert_eq!(count.get(), 0);
+        assert_eq!(*seen.borrow(), [(0, CountAction::Add(2)), (2, CountAction::Reset)]);
+    }
+
+    // A context set up like a running app's, and the channel its senders
+    // post to.
+    fn app_context() -> (StateContext, std::sync::mpsc::Receiver<Event>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let ctx = StateContext::new();
+        ctx.set_event_sender(tx);
+
+        (ctx, rx)
+    }
+
+    fn apply_pending(ctx: &StateContext, rx: &std::sync::mpsc::Receiver<Event>) {
+        while let Ok(event) = rx.try_recv() {
+            if let Event::State(update) = event {
+                ctx.apply_update(update);
+            }
+        }
+    }
+
+    #[test]
+    fn there_are_no_senders_outside_an_app() {
+        let ctx = StateContext::new();
+        let (_, set_count) = use_state(ctx, 0);
+
+        assert!(set_count.sender().is_none());
+    }
+
+    #[test]
+    fn senders_apply_updates_from_other_threads() {
+        let (ctx, rx) = app_context();
+        let (count, set_count) = use_state(ctx.clone(), 0);
+        let sender = set_count.sender().unwrap();
+
+        std::thread::spawn(move || {
+            sender.set(10);
+            sender.update(|count| count + 1);
+        }).join().unwrap();
+
+        apply_pending(&ctx, &rx);
+        assert_eq!(count.get(), 11);
+    }
+
+    #[test]
+    fn each_state_gets_its_own_sender_id() {
+        let (ctx, _rx) = app_context();
+        let (_, set_a) = use_state(ctx.clone(), 0);
+        let (_, set_b) = use_state(ctx.clone(), 0);
+
+        let a = set_a.sender().unwrap().id;
+        assert_eq!(set_a.clone().sender().unwrap().id, a);
+        assert_ne!(set_b.sender().unwrap().id, a);
+    }
+
+    #[test]
+    fn sender_ids_are_not_reused_after_unmounting() {
+        let (ctx, rx) = app_context();
+
+        let stale = {
+            let _root = ctx.scope("root");
+            let child = ctx.unkeyed();
+            let _child = child.scope("a");
+
+            use_state(child.clone(), 0).1.sender().unwrap()
+        };
+
+        render(&ctx, &[]);
+
+        let (count, set_count) = use_state(ctx.clone(), 0);
+        let sender = set_count.sender().unwrap();
+        assert_ne!(sender.id, stale.id);
+
+        stale.set(5);
+        apply_pending(&ctx, &rx);
+        assert_eq!(count.get(), 0);
+    }
+
+    #[test]
+    fn remote_setters_do_not_keep_the_state_alive() {
+        let (ctx, _rx) = app_context();
+        let (count, set_count) = use_state(ctx.clone(), 0);
+        let _sender = set_count.sender().unwrap();
+
+        let value = Rc::downgrade(&count.inner.value);
+        drop((count, set_count));
+
+        assert!(value.upgrade().is_none());
+    }
+
+    #[test]
+    fn cloned_updates_are_applied_once() {
+        let (ctx, rx) = app_context();
+        let (count, set_count) = use_state(ctx.clone(), 0);
+
+        set_count.sender().unwrap().update(|count| count + 1);
+
+        let event = rx.try_recv().unwrap();
+        let copy = event.clone();
+
+        for event in [event, copy] {
+            if let Event::State(update) = event {
+                ctx.apply_update(update);
+            }
+        }
+
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn try_update_leaves_the_state_alone_on_none() {
+        let (ctx, rx) = app_context();
+        let (log, take) = log();
+        let (count, set_count) = use_state(ctx.clone(), 0);
+
+        use_effect(ctx.clone(), move || log.borrow_mut().push(count.get().to_string()));
+        take();
+
+        let sender = set_count.sender().unwrap();
+        sender.try_update(|_| None);
+        apply_pending(&ctx, &rx);
+
+        assert!(take().is_empty());
+        assert!(!ctx.take_dirty());
+
+        sender.try_update(|count| Some(count + 1));
+        apply_pending(&ctx, &rx);
+
+        assert_eq!(take(), ["1"]);
     }
 }
@@ -0,0 +1,93 @@
+use crossterm::style::{Attribute, ContentStyle};
+
+pub use crossterm::style::Color;
+
+// How a component and everything inside it is drawn. Anything left unset is
+// inherited from the parent, so `Some(false)` turns off an inherited
+// attribute where `None` keeps it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underline: Option<bool>,
+    pub dim: Option<bool>,
+    pub reverse: Option<bool>,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = Some(true);
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = Some(true);
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = Some(true);
+        self
+    }
+
+    pub fn dim(mut self) -> Self {
+        self.dim = Some(true);
+        self
+    }
+
+    pub fn reverse(mut self) -> Self {
+        self.reverse = Some(true);
+        self
+    }
+
+    // Fills in whatever this style leaves unset from the parent's.
+    pub fn inherit(self, parent: &Style) -> Style {
+        Style {
+            fg: self.fg.or(parent.fg),
+            bg: self.bg.or(parent.bg),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underline: self.underline.or(parent.underline),
+            dim: self.dim.or(parent.dim),
+            reverse: self.reverse.or(parent.reverse),
+        }
+    }
+
+    pub fn content_style(&self) -> ContentStyle {
+        let mut style = ContentStyle::new();
+        style.foreground_color = self.fg;
+        style.background_color = self.bg;
+
+        let attributes = [
+            (self.bold, Attribute::Bold),
+            (self.italic, Attribute::Italic),
+            (self.underline, Attribute::Underlined),
+            (self.dim, Attribute::Dim),
+            (self.reverse, Attribute::Reverse),
+        ];
+
+        for (enabled, attribute) in attributes {
+            if enabled == Some(true) {
+                style.attributes.set(attribute);
+            }
+        }
+
+        style
+    }
+}
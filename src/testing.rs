@@ -0,0 +1,164 @@
+use std::{fmt, thread, time::{Duration, Instant}};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::{app::{App, AppBuilder}, backend::TestBackend, component::{Component, ComponentEvent}, events::Event, screen_buffer::{display_width, BufferCell, ScreenBuffer}, state::StateContext};
+
+// Mounts a root component against a TestBackend of a fixed size. Every event
+// sent through the harness is handled synchronously, together with any events
+// it causes (a mouse press queues an OnClick for instance), and the frame is
+// redrawn before returning.
+pub struct Harness {
+    app: App<TestBackend>,
+    quit: bool,
+}
+
+impl Harness {
+    pub fn new<F, P, C>(f: F, width: u16, height: u16) -> Self
+    where
+        F: Fn(StateContext, P) -> C + 'static,
+        P: Default,
+        C: Component + 'static,
+    {
+        Self::with_builder(AppBuilder::new(f), width, height)
+    }
+
+    // Mounts an app configured through a builder, to test its key bindings.
+    pub fn with_builder(builder: AppBuilder, width: u16, height: u16) -> Self {
+        let mut app = builder.build(TestBackend::new(width, height));
+        app.mount().unwrap();
+
+        Self { app, quit: false }
+    }
+
+    pub fn send(&mut self, event: Event) -> &mut Self {
+        self.handle(event);
+        self.poll()
+    }
+
+    // Handles events queued from elsewhere, like state senders on other
+    // threads, and redraws.
+    pub fn poll(&mut self) -> &mut Self {
+        while let Some(event) = self.app.event_manager().try_next() {
+            self.handle(event);
+        }
+
+        self.app.draw().unwrap();
+        self
+    }
+
+    // Polls until `condition` holds or `timeout` passes, returning whether it
+    // held.
+    pub fn wait_until<F: Fn(&Harness) -> bool>(&mut self, timeout: Duration, condition: F) -> bool {
+        let start = Instant::now();
+
+        loop {
+            self.poll();
+
+            if condition(self) {
+                return true;
+            }
+
+            if start.elapsed() >= timeout {
+                return false;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        if !self.app.handle_event(event) {
+            self.quit = true;
+        }
+    }
+
+    // Moves the clock timers run against forward and ticks, firing any timer
+    // that became due without having to wait for it.
+    pub fn advance(&mut self, duration: Duration) -> &mut Self {
+        self.app.state_context().advance_clock(duration);
+        self.send(Event::Tick)
+    }
+
+    pub fn dispatch(&mut self, event: ComponentEvent) -> &mut Self {
+        self.send(Event::Component(event))
+    }
+
+    pub fn key(&mut self, code: KeyCode) -> &mut Self {
+        self.key_with_modifiers(code, KeyModifiers::NONE)
+    }
+
+    pub fn key_with_modifiers(&mut self, code: KeyCode, modifiers: KeyModifiers) -> &mut Self {
+        self.send(Event::Key(KeyEvent::new(code, modifiers)))
+    }
+
+    pub fn paste(&mut self, text: &str) -> &mut Self {
+        self.send(Event::Paste(text.into()))
+    }
+
+    pub fn click(&mut self, x: usize, y: usize) -> &mut Self {
+        self.mouse(MouseEventKind::Down(MouseButton::Left), x, y)
+    }
+
+    pub fn mouse(&mut self, kind: MouseEventKind, x: usize, y: usize) -> &mut Self {
+        self.send(Event::Mouse(MouseEvent {
+            kind,
+            column: x as u16,
+            row: y as u16,
+            modifiers: KeyModifiers::NONE,
+        }))
+    }
+
+    pub fn click_text(&mut self, text: &str) -> &mut Self {
+        match self.find_text(text) {
+            Some((x, y)) => self.click(x, y),
+            None => panic!("could not find {:?} on screen:\n{}", text, self),
+        }
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> &mut Self {
+        self.app.backend_mut().resize(width, height);
+        self.send(Event::Resize(width, height))
+    }
+
+    // The position of the first cell showing `text`, counting double width
+    // graphemes as the two columns they take up.
+    pub fn find_text(&self, text: &str) -> Option<(usize, usize)> {
+        (0..self.buffer().height()).find_map(|y| {
+            let row = self.row(y);
+            let i = row.find(text)?;
+
+            Some((display_width(&row[..i]), y))
+        })
+    }
+
+    pub fn contains_text(&self, text: &str) -> bool {
+        self.find_text(text).is_some()
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<&BufferCell> {
+        self.buffer().get(x, y)
+    }
+
+    pub fn row(&self, y: usize) -> String {
+        self.buffer().row_text(y)
+    }
+
+    pub fn buffer(&self) -> &ScreenBuffer {
+        self.app.backend().buffer()
+    }
+
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+}
+
+impl fmt::Display for Harness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.buffer().height() {
+            writeln!(f, "{}", self.row(y))?;
+        }
+
+        Ok(())
+    }
+}
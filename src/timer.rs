@@ -0,0 +1,56 @@
+use std::{cell::RefCell, rc::Rc, time::{Duration, SystemTime}};
+
+use crate::state::{use_effect, use_state, GetState, StateContext, Callback};
+
+// Calls `f` every `period`, driven by the ticks of the event loop, until the
+// component is unmounted. Changing `period` restarts it.
+pub fn use_interval<F: Fn() + 'static>(ctx: StateContext, period: Duration, f: F) {
+    use_timer(ctx, period, Some(period), f);
+}
+
+// Calls `f` once after `delay`, unless the component is unmounted first.
+// Changing `delay` starts the wait over, even if it already fired.
+pub fn use_timeout<F: Fn() + 'static>(ctx: StateContext, delay: Duration, f: F) {
+    use_timer(ctx, delay, None, f);
+}
+
+// The current time, refreshed every `resolution`.
+pub fn use_now(ctx: StateContext, resolution: Duration) -> GetState<SystemTime> {
+    let now = {
+        let ctx = ctx.clone();
+        move || SystemTime::now() + ctx.clock_offset()
+    };
+
+    let (time, set_time) = use_state(ctx.clone(), now());
+
+    use_interval(ctx.clone(), resolution, move || set_time.set(now()));
+
+    time
+}
+
+// Rendering with a different delay or interval starts the timer over.
+fn use_timer<F: Fn() + 'static>(ctx: StateContext, delay: Duration, interval: Option<Duration>, f: F) {
+    let f: Rc<dyn Fn()> = Rc::new(f);
+
+    // Swapped on every render so the timer calls the latest closure
+    let callback: Callback = ctx.use_hook(|| RefCell::new(f.clone()));
+    *callback.borrow_mut() = f;
+
+    let (timing, set_timing) = use_state(ctx.clone(), (delay, interval));
+
+    if timing.get_untracked() != (delay, interval) {
+        set_timing.set((delay, interval));
+    }
+
+    use_effect(ctx.clone(), {
+        let ctx = ctx.clone();
+
+        move || {
+            let (delay, interval) = timing.get();
+            let id = ctx.add_timer(delay, interval, callback.clone());
+
+            let ctx = ctx.clone();
+            move || ctx.remove_timer(id)
+        }
+    });
+}
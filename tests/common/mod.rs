@@ -0,0 +1,21 @@
+use tolid::prelude::*;
+
+// Two buttons either side of a count, shared by the tests that click or focus
+// them.
+#[component]
+pub fn Counter() -> impl Component {
+    let (count, set_count) = use_state(ctx.clone(), 0_i64);
+
+    let increment = cm!(set_count |_| set_count.update(|count| count + 1));
+    let decrement = cm!(set_count |_| set_count.update(|count| count - 1));
+
+    let value = cm!(count || format!("Counter: {}", count.get()));
+
+    ui! {
+        <Center direction={Direction::Column} border={true}>
+            <Button padding={(0, 1, 0, 1)} border={true} on:click={decrement} value={"<<".into()} />
+            <Text padding={(1, 2, 1, 2)} value={value} />
+            <Button padding={(0, 1, 0, 1)} border={true} on:click={increment} value={">>".into()} />
+        </Center>
+    }
+}
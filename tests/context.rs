@@ -0,0 +1,86 @@
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+#[derive(Clone)]
+struct Theme(&'static str);
+
+#[component]
+fn Leaf() -> impl Component {
+    let theme = use_context::<Theme>(ctx.clone()).map_or("none", |theme| theme.0);
+    let count = use_context::<GetState<i64>>(ctx.clone()).unwrap();
+
+    let value = cm!(count || format!("count {}", count.get()));
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Text value={format!("theme {theme}")} />
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+#[component]
+fn App() -> impl Component {
+    let (count, set_count) = use_state(ctx.clone(), 0_i64);
+    let increment = cm!(set_count |_| set_count.update(|count| count + 1));
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Button on:click={increment} value={"inc".to_string()} />
+            <Provider value={Some(count.clone())}>
+                <Provider value={Some(Theme("dark"))}>
+                    <Leaf />
+                </Provider>
+                <Provider value={Some(Theme("light"))}>
+                    <Leaf />
+                </Provider>
+            </Provider>
+            <Text value={format!("after {}", use_context::<Theme>(ctx.clone()).map_or("none", |theme| theme.0))} />
+        </Stack>
+    }
+}
+
+#[test]
+fn children_see_the_nearest_provided_value() {
+    let harness = Harness::new(App, 60, 4);
+
+    assert!(harness.contains_text("theme dark"), "{harness}");
+    assert!(harness.contains_text("theme light"), "{harness}");
+}
+
+#[test]
+fn provided_values_stay_inside_the_provider() {
+    let harness = Harness::new(App, 60, 4);
+
+    assert!(harness.contains_text("after none"), "{harness}");
+}
+
+#[test]
+fn provided_state_updates_consumers() {
+    let mut harness = Harness::new(App, 60, 4);
+
+    harness.click_text("inc");
+    assert!(harness.contains_text("count 1"), "{harness}");
+}
+
+// A component of its own called Provider is not treated specially.
+mod custom {
+    use tolid::prelude::*;
+    use tolid::testing::Harness;
+
+    #[component]
+    fn Provider(value: String) -> impl Component {
+        ui! { <Text value={value} /> }
+    }
+
+    #[component]
+    fn App() -> impl Component {
+        ui! { <Provider value={"custom".to_string()} /> }
+    }
+
+    #[test]
+    fn renders_like_any_other_component() {
+        let harness = Harness::new(App, 20, 1);
+        assert!(harness.contains_text("custom"), "{harness}");
+    }
+}
@@ -0,0 +1,88 @@
+use crossterm::{event::{KeyCode, KeyEvent}, style::Attribute};
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+mod common;
+
+use common::Counter;
+
+fn is_focused(harness: &Harness, text: &str) -> bool {
+    let (x, y) = harness.find_text(text).unwrap();
+    harness.cell(x, y).unwrap().style().attributes.has(Attribute::Reverse)
+}
+
+#[test]
+fn tab_moves_focus_in_render_order() {
+    let mut harness = Harness::new(Counter, 40, 10);
+
+    harness.key(KeyCode::Tab);
+    assert!(is_focused(&harness, "<<"), "{harness}");
+
+    harness.key(KeyCode::Tab);
+    assert!(is_focused(&harness, ">>"), "{harness}");
+    assert!(!is_focused(&harness, "<<"), "{harness}");
+
+    harness.key(KeyCode::BackTab);
+    assert!(is_focused(&harness, "<<"), "{harness}");
+}
+
+#[test]
+fn enter_and_space_click_the_focused_button() {
+    let mut harness = Harness::new(Counter, 40, 10);
+
+    harness.key(KeyCode::Tab).key(KeyCode::Tab).key(KeyCode::Enter).key(KeyCode::Char(' '));
+    assert!(harness.contains_text("Counter: 2"), "{harness}");
+}
+
+#[test]
+fn clicking_a_button_focuses_it() {
+    let mut harness = Harness::new(Counter, 40, 10);
+
+    harness.click_text(">>").key(KeyCode::Enter);
+    assert!(harness.contains_text("Counter: 2"), "{harness}");
+}
+
+#[test]
+fn unhandled_arrow_keys_move_focus() {
+    let mut harness = Harness::new(Counter, 40, 10);
+
+    harness.key(KeyCode::Right);
+    assert!(is_focused(&harness, "<<"), "{harness}");
+
+    harness.key(KeyCode::Down);
+    assert!(is_focused(&harness, ">>"), "{harness}");
+
+    harness.key(KeyCode::Up);
+    assert!(is_focused(&harness, "<<"), "{harness}");
+}
+
+#[component]
+fn List() -> impl Component {
+    let (selected, set_selected) = use_state(ctx.clone(), 0_i64);
+
+    let select = cm!(set_selected |key: UiEvent<KeyEvent>| match key.code {
+        KeyCode::Down => set_selected.update(|selected| selected + 1),
+        KeyCode::Up => set_selected.update(|selected| selected - 1),
+        _ => {},
+    });
+
+    let value = cm!(selected || format!("selected {}", selected.get()));
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Button value={"list".into()} on:keydown={select} />
+            <Button value={"other".into()} />
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+#[test]
+fn arrow_keys_handled_by_a_component_stay_with_it() {
+    let mut harness = Harness::new(List, 40, 3);
+
+    harness.key(KeyCode::Tab).key(KeyCode::Down).key(KeyCode::Down);
+
+    assert!(harness.contains_text("selected 2"), "{harness}");
+    assert!(is_focused(&harness, "list"), "{harness}");
+}
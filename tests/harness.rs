@@ -0,0 +1,62 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use tolid::testing::Harness;
+
+mod common;
+
+use common::Counter;
+
+#[test]
+fn renders_the_first_frame_on_mount() {
+    let harness = Harness::new(Counter, 40, 10);
+
+    assert!(harness.contains_text("Counter: 0"), "{harness}");
+    assert!(harness.contains_text(">>"), "{harness}");
+}
+
+#[test]
+fn clicking_a_button_updates_the_frame() {
+    let mut harness = Harness::new(Counter, 40, 10);
+
+    harness.click_text(">>");
+    assert!(harness.contains_text("Counter: 1"), "{harness}");
+
+    harness.click_text("<<").click_text("<<");
+    assert!(harness.contains_text("Counter: -1"), "{harness}");
+}
+
+#[test]
+fn clicking_outside_the_buttons_does_nothing() {
+    let mut harness = Harness::new(Counter, 40, 10);
+
+    harness.click(0, 0);
+    assert!(harness.contains_text("Counter: 0"), "{harness}");
+}
+
+#[test]
+fn find_text_reports_the_cell_it_starts_in() {
+    let harness = Harness::new(Counter, 40, 10);
+    let (x, y) = harness.find_text("Counter").unwrap();
+
+    assert_eq!(harness.cell(x, y).unwrap().char(), 'C');
+    assert!(harness.row(y).contains("Counter: 0"));
+}
+
+#[test]
+fn resizing_redraws_at_the_new_size() {
+    let mut harness = Harness::new(Counter, 40, 10);
+
+    harness.resize(60, 12);
+
+    assert_eq!(harness.buffer().width(), 60);
+    assert_eq!(harness.buffer().height(), 12);
+    assert!(harness.contains_text("Counter: 0"), "{harness}");
+}
+
+#[test]
+fn ctrl_c_quits() {
+    let mut harness = Harness::new(Counter, 40, 10);
+    assert!(!harness.has_quit());
+
+    harness.key_with_modifiers(KeyCode::Char('c'), KeyModifiers::CONTROL);
+    assert!(harness.has_quit());
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+#[component]
+fn App() -> impl Component {
+    let (log, set_log) = use_state(ctx.clone(), String::new());
+
+    use_action(ctx.clone(), "top", cm!(set_log || set_log.update(|log| format!("{log}T"))));
+    use_action(ctx.clone(), "save", cm!(set_log || set_log.update(|log| format!("{log}S"))));
+
+    let exit = use_exit(ctx.clone());
+
+    let on_key = cm!(set_log |key: UiEvent<KeyEvent>| {
+        if let KeyCode::Char(c) = key.code {
+            set_log.update(|log| format!("{log}{c}"));
+        }
+
+        if key.code == KeyCode::Esc {
+            exit.exit();
+        }
+    });
+
+    let value = cm!(log || format!("log:{}", log.get()));
+
+    ui! {
+        <Stack on:key={on_key}>
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+fn builder() -> AppBuilder {
+    AppBuilder::new(App)
+        .bind([KeyCombo::from('g'), KeyCombo::from('g')], Action::Custom("top"))
+        .bind(KeyCombo::ctrl('s'), Action::Custom("save"))
+}
+
+#[test]
+fn bound_keys_run_their_action() {
+    let mut harness = Harness::with_builder(builder(), 40, 5);
+
+    harness.key(KeyCode::Char('g')).key(KeyCode::Char('g'));
+    harness.key_with_modifiers(KeyCode::Char('s'), KeyModifiers::CONTROL);
+
+    assert!(harness.contains_text("log:TS"), "{harness}");
+}
+
+#[test]
+fn unbound_keys_go_to_the_components() {
+    let mut harness = Harness::with_builder(builder(), 40, 5);
+
+    harness.key(KeyCode::Char('q'));
+
+    assert!(!harness.has_quit());
+    assert!(harness.contains_text("log:q"), "{harness}");
+}
+
+#[test]
+fn broken_sequences_replay_their_keys() {
+    let mut harness = Harness::with_builder(builder(), 40, 5);
+
+    harness.key(KeyCode::Char('g')).key(KeyCode::Char('x'));
+
+    assert!(harness.contains_text("log:gx"), "{harness}");
+}
+
+#[test]
+fn unfinished_sequences_time_out() {
+    let mut harness = Harness::with_builder(builder(), 40, 5);
+
+    harness.key(KeyCode::Char('g'));
+    assert!(harness.contains_text("log:"), "{harness}");
+    assert!(!harness.contains_text("log:g"), "{harness}");
+
+    harness.advance(Duration::from_secs(1));
+    assert!(harness.contains_text("log:g"), "{harness}");
+
+    harness.key(KeyCode::Char('g'));
+    harness.advance(Duration::from_millis(100));
+    harness.key(KeyCode::Char('g'));
+    assert!(harness.contains_text("log:gT"), "{harness}");
+}
+
+#[test]
+fn rebinding_ctrl_c_frees_it_from_quitting() {
+    let builder = builder().bind(KeyCombo::ctrl('c'), Action::Custom("save"));
+    let mut harness = Harness::with_builder(builder, 40, 5);
+
+    harness.key_with_modifiers(KeyCode::Char('c'), KeyModifiers::CONTROL);
+    assert!(!harness.has_quit());
+    assert!(harness.contains_text("log:S"), "{harness}");
+
+    harness.key(KeyCode::Esc);
+    assert!(harness.has_quit());
+}
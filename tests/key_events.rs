@@ -0,0 +1,63 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use tolid::events::Event;
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+// Each handler adds its name and the key's character to the log.
+fn logger(set_log: &SetState<String>, name: &'static str) -> impl FnMut(UiEvent<KeyEvent>) + 'static {
+    let set_log = set_log.clone();
+
+    move |key: UiEvent<KeyEvent>| {
+        if let KeyCode::Char(c) = key.code {
+            set_log.update(|log| format!("{log}{name}{c}"));
+        }
+    }
+}
+
+#[component]
+fn App() -> impl Component {
+    let (log, set_log) = use_state(ctx.clone(), String::new());
+    let value = cm!(log || format!("log:{}", log.get()));
+
+    let down = cm!(set_log |_| set_log.update(|log| format!("{log}D")));
+
+    ui! {
+        <Stack on:key={logger(&set_log, "r")}>
+            <Stack on:keydown={down}>
+                <Button value={"A".into()} on:key={logger(&set_log, "a")} />
+            </Stack>
+            <Button value={"B".into()} on:key={logger(&set_log, "b")} />
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+#[test]
+fn without_focus_keys_go_to_the_root() {
+    let mut harness = Harness::new(App, 40, 10);
+
+    harness.key(KeyCode::Char('x'));
+    assert!(harness.contains_text("log:rx"), "{harness}");
+}
+
+#[test]
+fn keys_bubble_from_the_focused_component() {
+    let mut harness = Harness::new(App, 40, 10);
+
+    harness.key(KeyCode::Tab).key(KeyCode::Char('y'));
+    assert!(harness.contains_text("log:ayDry"), "{harness}");
+
+    harness.key(KeyCode::Tab).key(KeyCode::Char('z'));
+    assert!(harness.contains_text("log:ayDrybzrz"), "{harness}");
+}
+
+#[test]
+fn keydown_skips_releases() {
+    let mut harness = Harness::new(App, 40, 10);
+
+    let mut release = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE);
+    release.kind = KeyEventKind::Release;
+
+    harness.key(KeyCode::Tab).send(Event::Key(release));
+    assert!(harness.contains_text("log:awrw"), "{harness}");
+}
@@ -0,0 +1,110 @@
+use crossterm::{event::KeyCode, style::Attribute};
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+#[component]
+fn Item(label: String) -> impl Component {
+    let (count, set_count) = use_state(ctx.clone(), 0_i64);
+    let increment = cm!(set_count |_| set_count.update(|count| count + 1));
+
+    ui! { <Button on:click={increment} value={format!("{label}={}", count.get())} /> }
+}
+
+#[component]
+fn List() -> impl Component {
+    let (reversed, set_reversed) = use_state(ctx.clone(), false);
+    let (hidden, set_hidden) = use_state(ctx.clone(), false);
+
+    let reverse = cm!([reversed, set_reversed] |_| set_reversed.set(!reversed.get()));
+    let hide = cm!([hidden, set_hidden] |_| set_hidden.set(!hidden.get()));
+
+    let mut names = vec!["x", "y", "z"];
+
+    if reversed.get() {
+        names.reverse();
+    }
+
+    if hidden.get() {
+        names.retain(|name| *name != "y");
+    }
+
+    let items: Vec<Box<dyn Component>> = names.into_iter()
+        .map(|name| Box::new(ui! { <Item key={name} label={name.to_string()} /> }) as Box<dyn Component>)
+        .collect();
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Button on:click={reverse} value={"rev".to_string()} />
+            <Button on:click={hide} value={"hide".to_string()} />
+            <Stack direction={Direction::Column}>{items}</Stack>
+        </Stack>
+    }
+}
+
+#[test]
+fn keyed_state_follows_its_item() {
+    let mut harness = Harness::new(List, 60, 3);
+
+    harness.click_text("x=0");
+    harness.click_text("y=0").click_text("y=1");
+    harness.click_text("rev");
+
+    let row = harness.row(0);
+    assert!(row.find("z=0").unwrap() < row.find("y=2").unwrap(), "{harness}");
+    assert!(harness.contains_text("x=1"), "{harness}");
+}
+
+#[test]
+fn removed_items_lose_their_state() {
+    let mut harness = Harness::new(List, 60, 3);
+
+    harness.click_text("y=0");
+    harness.click_text("hide");
+    assert!(!harness.contains_text("y="), "{harness}");
+
+    harness.click_text("hide");
+    assert!(harness.contains_text("y=0"), "{harness}");
+}
+
+#[component]
+fn Rows() -> impl Component {
+    let (reversed, set_reversed) = use_state(ctx.clone(), false);
+    use_action(ctx.clone(), "reverse", cm!([reversed, set_reversed] || set_reversed.set(!reversed.get())));
+
+    let mut names = vec!["a", "b"];
+
+    if reversed.get() {
+        names.reverse();
+    }
+
+    let rows: Vec<Box<dyn Component>> = names.into_iter()
+        .map(|name| Box::new(ui! {
+            <Stack key={name} focusable={true}>
+                <Text value={name} />
+            </Stack>
+        }) as Box<dyn Component>)
+        .collect();
+
+    ui! {
+        <Stack>{rows}</Stack>
+    }
+}
+
+fn is_reversed(harness: &Harness, text: &str) -> bool {
+    let (x, y) = harness.find_text(text).unwrap();
+    harness.cell(x, y).unwrap().style().attributes.has(Attribute::Reverse)
+}
+
+#[test]
+fn keyed_stacks_keep_focus_when_they_move() {
+    let builder = AppBuilder::new(Rows).bind('r', Action::Custom("reverse"));
+    let mut harness = Harness::with_builder(builder, 10, 2);
+
+    harness.key(KeyCode::Tab);
+    assert!(is_reversed(&harness, "a"), "{harness}");
+
+    harness.key(KeyCode::Char('r'));
+    assert_eq!(harness.row(0).trim(), "b", "{harness}");
+    assert!(is_reversed(&harness, "a"), "{harness}");
+    assert!(!is_reversed(&harness, "b"), "{harness}");
+}
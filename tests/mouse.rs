@@ -0,0 +1,127 @@
+use crossterm::event::{MouseButton, MouseEventKind};
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+#[component]
+fn App() -> impl Component {
+    let (log, set_log) = use_state(ctx.clone(), String::new());
+    let value = cm!(log || format!("log:{}", log.get()));
+
+    // Logs the tag with the position relative to the box
+    let logger = |tag: &'static str| {
+        let set_log = set_log.clone();
+        move |mouse: UiEvent<Mouse>| set_log.update(|log| format!("{log}{tag}{},{};", mouse.x, mouse.y))
+    };
+
+    // Click handlers may ignore the event entirely
+    let click = cm!(set_log || set_log.update(|log| format!("{log}C;")));
+
+    ui! {
+        <Stack>
+            <Stack width={StackWidth::Exact(3)} direction={Direction::Column}>
+                <Stack
+                    width={StackWidth::Exact(4)}
+                    on:hover={logger("H")}
+                    on:leave={logger("L")}
+                    on:scroll={logger("S")}
+                    on:drag={logger("D")}
+                    on:release={logger("R")}
+                    on:contextmenu={logger("M")}
+                    on:click={click}
+                >
+                    <Text value={"box"} />
+                </Stack>
+            </Stack>
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+fn log(harness: &Harness) -> String {
+    harness.row(3).trim_end().trim_start_matches("log:").to_string()
+}
+
+#[test]
+fn hover_and_leave_follow_the_mouse() {
+    let mut harness = Harness::new(App, 40, 6);
+
+    harness.mouse(MouseEventKind::Moved, 20, 0);
+    harness.mouse(MouseEventKind::Moved, 1, 1).mouse(MouseEventKind::Moved, 2, 1);
+    harness.mouse(MouseEventKind::Moved, 20, 0);
+
+    assert_eq!(log(&harness), "H1,1;L20,0;");
+}
+
+#[test]
+fn scroll_is_relative_to_the_component() {
+    let mut harness = Harness::new(App, 40, 6);
+
+    harness.mouse(MouseEventKind::ScrollDown, 2, 1);
+    assert_eq!(log(&harness), "H2,1;S2,1;");
+}
+
+#[test]
+fn drags_stay_with_the_component_they_started_in() {
+    let mut harness = Harness::new(App, 40, 6);
+
+    harness.mouse(MouseEventKind::Down(MouseButton::Left), 1, 0);
+    harness.mouse(MouseEventKind::Drag(MouseButton::Left), 10, 0);
+    harness.mouse(MouseEventKind::Up(MouseButton::Left), 10, 0);
+
+    // Once released, dragging elsewhere doesn't reach the box
+    harness.mouse(MouseEventKind::Drag(MouseButton::Left), 20, 0);
+
+    assert_eq!(log(&harness), "H1,0;C;L10,0;D10,0;R10,0;");
+}
+
+#[test]
+fn any_button_clicks_by_default() {
+    let mut harness = Harness::new(App, 40, 6);
+
+    harness.mouse(MouseEventKind::Down(MouseButton::Right), 1, 0);
+    harness.mouse(MouseEventKind::Down(MouseButton::Middle), 1, 0);
+
+    assert_eq!(log(&harness), "H1,0;M1,0;C;C;");
+}
+
+#[test]
+fn clicks_can_be_limited_to_one_button() {
+    let builder = AppBuilder::new(App).click_button(MouseButton::Left);
+    let mut harness = Harness::with_builder(builder, 40, 6);
+
+    harness.mouse(MouseEventKind::Down(MouseButton::Right), 1, 0);
+    harness.mouse(MouseEventKind::Down(MouseButton::Left), 1, 0);
+
+    assert_eq!(log(&harness), "H1,0;M1,0;C;");
+}
+
+#[component]
+fn Offset() -> impl Component {
+    let (log, set_log) = use_state(ctx.clone(), String::new());
+    let value = cm!(log || format!("log:{}", log.get()));
+
+    let logger = |tag: &'static str| {
+        let set_log = set_log.clone();
+        move |mouse: UiEvent<Mouse>| set_log.update(|log| format!("{log}{tag}{},{};", mouse.x, mouse.y))
+    };
+
+    ui! {
+        <Stack direction={Direction::Row} padding={(1, 0, 0, 5)}>
+            <Stack on:drag={logger("D")} on:release={logger("R")}>
+                <Text value={"box"} />
+            </Stack>
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+#[test]
+fn releasing_a_drag_reaches_the_component_it_started_in() {
+    let mut harness = Harness::new(Offset, 40, 4);
+
+    harness.mouse(MouseEventKind::Down(MouseButton::Left), 6, 1);
+    harness.mouse(MouseEventKind::Drag(MouseButton::Left), 12, 3);
+    harness.mouse(MouseEventKind::Up(MouseButton::Left), 12, 3);
+
+    assert_eq!(harness.row(2).trim(), "log:D7,2;R7,2;", "{harness}");
+}
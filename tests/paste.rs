@@ -0,0 +1,48 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+#[component]
+fn App() -> impl Component {
+    let (log, set_log) = use_state(ctx.clone(), String::new());
+    let value = cm!(log || format!("log:{}", log.get()));
+
+    let on_button = cm!(set_log |text: UiEvent<String>| set_log.update(|log| format!("{log}button[{}]", *text)));
+    let on_root = cm!(set_log |text: UiEvent<String>| set_log.update(|log| format!("{log}root[{}]", *text)));
+
+    ui! {
+        <Stack direction={Direction::Column} on:paste={on_root}>
+            <Button value={"Paste here".into()} on:paste={on_button} />
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+#[test]
+fn pastes_reach_only_the_root_without_focus() {
+    let mut harness = Harness::new(App, 40, 5);
+
+    harness.paste("hello");
+    assert!(harness.contains_text("log:root[hello]"), "{harness}");
+}
+
+#[test]
+fn pastes_go_to_the_focused_component_and_bubble_up() {
+    let mut harness = Harness::new(App, 40, 5);
+
+    harness.key(KeyCode::Tab).paste("hello");
+    assert!(harness.contains_text("log:button[hello]root[hello]"), "{harness}");
+}
+
+#[test]
+fn pasted_text_arrives_whole_rather_than_as_keys() {
+    let mut harness = Harness::new(App, 40, 5);
+
+    // Pasting a line ending with Ctrl-C's character neither quits nor splits it
+    harness.paste("a b\u{3}");
+    assert!(harness.contains_text("log:root[a b\u{3}]"), "{harness}");
+    assert!(!harness.has_quit());
+
+    harness.key_with_modifiers(KeyCode::Char('c'), KeyModifiers::CONTROL);
+    assert!(harness.has_quit());
+}
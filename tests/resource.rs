@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[component]
+fn App() -> impl Component {
+    let (id, set_id) = use_state(ctx.clone(), 1_u64);
+
+    let item = use_resource(ctx.clone(), cm!(id || {
+        let id = id.get();
+
+        move || {
+            // The second load is still running when the third replaces it
+            std::thread::sleep(Duration::from_millis(if id == 2 { 200 } else { 20 }));
+
+            match id {
+                4 => Err(format!("bad {id}")),
+                5 => panic!("boom {id}"),
+                _ => Ok(format!("item {id}")),
+            }
+        }
+    }));
+
+    let next = cm!(set_id |_| set_id.update(|id| id + 1));
+
+    let value = cm!(item || match item.get() {
+        Resource::Loading => "loading".to_string(),
+        Resource::Ready(item) => item,
+        Resource::Failed(error) => format!("failed {error}"),
+        Resource::Panicked(message) => format!("panicked {message}"),
+    });
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Button on:click={next} value={"next".to_string()} />
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+#[test]
+fn loads_in_the_background() {
+    let mut harness = Harness::new(App, 40, 2);
+    assert!(harness.contains_text("loading"), "{harness}");
+
+    assert!(harness.wait_until(TIMEOUT, |harness| harness.contains_text("item 1")), "{harness}");
+}
+
+#[test]
+fn superseded_loads_are_dropped() {
+    let mut harness = Harness::new(App, 40, 2);
+    assert!(harness.wait_until(TIMEOUT, |harness| harness.contains_text("item 1")), "{harness}");
+
+    harness.click_text("next");
+    assert!(harness.contains_text("loading"), "{harness}");
+
+    harness.click_text("next");
+    assert!(harness.wait_until(TIMEOUT, |harness| harness.contains_text("item 3")), "{harness}");
+
+    std::thread::sleep(Duration::from_millis(300));
+    harness.poll();
+    assert!(harness.contains_text("item 3"), "{harness}");
+}
+
+#[test]
+fn errors_and_panics_end_loading() {
+    let mut harness = Harness::new(App, 40, 2);
+
+    harness.click_text("next").click_text("next").click_text("next");
+    assert!(harness.wait_until(TIMEOUT, |harness| harness.contains_text("failed bad 4")), "{harness}");
+
+    harness.click_text("next");
+    assert!(harness.wait_until(TIMEOUT, |harness| harness.contains_text("panicked boom 5")), "{harness}");
+}
@@ -0,0 +1,51 @@
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+#[component]
+fn Child(label: String) -> impl Component {
+    let (count, set_count) = use_state(ctx.clone(), 0_i64);
+    let increment = cm!(set_count |_| set_count.update(|count| count + 1));
+
+    ui! { <Button on:click={increment} value={format!("{label}={}", count.get())} /> }
+}
+
+#[component]
+fn Parent() -> impl Component {
+    let (vertical, set_vertical) = use_state(ctx.clone(), false);
+    let toggle = cm!([vertical, set_vertical] |_| set_vertical.set(!vertical.get()));
+
+    let (label, direction) = match vertical.get() {
+        true => ("vertical", Direction::Row),
+        false => ("horizontal", Direction::Column),
+    };
+
+    ui! {
+        <Center direction={direction}>
+            <Button on:click={toggle} value={label.to_string()} />
+            <Child label={"a".to_string()} />
+            <Child label={"b".to_string()} />
+        </Center>
+    }
+}
+
+#[test]
+fn child_state_change_redraws() {
+    let mut harness = Harness::new(Parent, 60, 10);
+
+    harness.click_text("b=0").click_text("b=1");
+
+    assert!(harness.contains_text("b=2"), "{harness}");
+    assert!(harness.contains_text("a=0"), "{harness}");
+}
+
+#[test]
+fn children_keep_their_state_when_the_parent_rebuilds() {
+    let mut harness = Harness::new(Parent, 60, 10);
+
+    harness.click_text("a=0");
+    harness.click_text("horizontal");
+
+    assert!(harness.contains_text("vertical"), "{harness}");
+    assert!(harness.contains_text("a=1"), "{harness}");
+    assert!(harness.contains_text("b=0"), "{harness}");
+}
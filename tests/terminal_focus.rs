@@ -0,0 +1,55 @@
+use tolid::events::Event;
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+#[component]
+fn App() -> impl Component {
+    let focused = use_terminal_focus(ctx.clone());
+    let (changes, set_changes) = use_state(ctx.clone(), 0);
+
+    // Counts the times the effect sees the focus change
+    use_effect(ctx.clone(), cm!([focused, set_changes] || {
+        focused.get();
+        set_changes.update(|changes| changes + 1);
+    }));
+
+    // Read during render, so only updated when the component re-renders
+    let rendered = format!("rendered:{}", focused.get());
+    let value = cm!([focused, changes] || format!("focused:{} effects:{}", focused.get(), changes.get()));
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Text value={value} />
+            <Text value={rendered} />
+        </Stack>
+    }
+}
+
+#[test]
+fn the_terminal_starts_focused() {
+    let harness = Harness::new(App, 40, 5);
+
+    assert!(harness.contains_text("focused:true effects:1"), "{harness}");
+    assert!(harness.contains_text("rendered:true"), "{harness}");
+}
+
+#[test]
+fn focus_changes_re_render_and_rerun_effects() {
+    let mut harness = Harness::new(App, 40, 5);
+
+    harness.send(Event::FocusLost);
+    assert!(harness.contains_text("focused:false effects:2"), "{harness}");
+    assert!(harness.contains_text("rendered:false"), "{harness}");
+
+    harness.send(Event::FocusGained);
+    assert!(harness.contains_text("focused:true effects:3"), "{harness}");
+    assert!(harness.contains_text("rendered:true"), "{harness}");
+}
+
+#[test]
+fn repeated_reports_change_nothing() {
+    let mut harness = Harness::new(App, 40, 5);
+
+    harness.send(Event::FocusLost).send(Event::FocusLost);
+    assert!(harness.contains_text("focused:false effects:2"), "{harness}");
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[component]
+fn Ticker() -> impl Component {
+    let (ticks, set_ticks) = use_state(ctx.clone(), 0_i64);
+    let (fast, set_fast) = use_state(ctx.clone(), false);
+
+    let period = if fast.get() { ms(50) } else { ms(100) };
+    use_interval(ctx.clone(), period, cm!(set_ticks || set_ticks.update(|ticks| ticks + 1)));
+
+    let speed_up = cm!(set_fast |_| set_fast.set(true));
+    let value = cm!(ticks || format!("ticks {}", ticks.get()));
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Button on:click={speed_up} value={"fast".to_string()} />
+            <Text value={value} />
+        </Stack>
+    }
+}
+
+#[component]
+fn App() -> impl Component {
+    let (show, set_show) = use_state(ctx.clone(), true);
+    let (done, set_done) = use_state(ctx.clone(), false);
+
+    use_timeout(ctx.clone(), ms(250), cm!(set_done || set_done.set(true)));
+
+    let toggle = cm!([show, set_show] |_| set_show.set(!show.get()));
+    let done = cm!(done || format!("done {}", done.get()));
+
+    let ticker: Vec<Box<dyn Component>> = match show.get() {
+        true => vec![Box::new(ui! { <Ticker /> })],
+        false => vec![],
+    };
+
+    ui! {
+        <Stack direction={Direction::Column}>
+            <Button on:click={toggle} value={"toggle".to_string()} />
+            <Text value={done} />
+            <Stack>{ticker}</Stack>
+        </Stack>
+    }
+}
+
+#[test]
+fn intervals_fire_every_period() {
+    let mut harness = Harness::new(App, 40, 6);
+    assert!(harness.contains_text("ticks 0"), "{harness}");
+
+    harness.advance(ms(100));
+    assert!(harness.contains_text("ticks 1"), "{harness}");
+
+    harness.advance(ms(100));
+    assert!(harness.contains_text("ticks 2"), "{harness}");
+}
+
+#[test]
+fn timeouts_fire_once() {
+    let mut harness = Harness::new(App, 40, 6);
+
+    harness.advance(ms(200));
+    assert!(harness.contains_text("done false"), "{harness}");
+
+    harness.advance(ms(100));
+    assert!(harness.contains_text("done true"), "{harness}");
+}
+
+#[test]
+fn timers_stop_when_unmounted() {
+    let mut harness = Harness::new(App, 40, 6);
+
+    harness.click_text("toggle");
+    harness.advance(ms(500));
+    harness.click_text("toggle");
+
+    assert!(harness.contains_text("ticks 0"), "{harness}");
+}
+
+#[test]
+fn changing_the_period_restarts_the_interval() {
+    let mut harness = Harness::new(App, 40, 6);
+
+    harness.advance(ms(60));
+    harness.click_text("fast");
+
+    harness.advance(ms(40));
+    assert!(harness.contains_text("ticks 0"), "{harness}");
+
+    harness.advance(ms(10));
+    assert!(harness.contains_text("ticks 1"), "{harness}");
+
+    harness.advance(ms(50));
+    assert!(harness.contains_text("ticks 2"), "{harness}");
+}
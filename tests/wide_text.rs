@@ -0,0 +1,61 @@
+use tolid::prelude::*;
+use tolid::testing::Harness;
+
+const TEXTS: [&str; 4] = ["日本語x", "ab", "a日b", "e\u{301}👍🏽!"];
+
+#[component]
+fn App() -> impl Component {
+    let (step, set_step) = use_state(ctx.clone(), 0_usize);
+
+    let value = cm!(step || TEXTS[step.get() % TEXTS.len()].to_string());
+    let next = cm!(set_step || set_step.update(|step| step + 1));
+
+    ui! {
+        <Stack>
+            <Stack direction={Direction::Column}>
+                <Text value={value} />
+                <Text value={"|"} />
+            </Stack>
+            <Button value={"next".into()} on:click={next} />
+        </Stack>
+    }
+}
+
+// The first row as drawn from scratch at the given step
+fn fresh_row(step: usize) -> String {
+    let mut harness = Harness::new(App, 20, 4);
+
+    for _ in 0..step {
+        harness.click_text("next");
+    }
+
+    harness.row(0)
+}
+
+#[test]
+fn wide_graphemes_take_two_columns() {
+    let harness = Harness::new(App, 20, 4);
+
+    assert!(harness.row(0).starts_with("日本語x|"), "{harness}");
+    assert_eq!(harness.find_text("x|"), Some((6, 0)));
+    assert!(harness.cell(1, 0).unwrap().is_continuation());
+}
+
+#[test]
+fn clusters_are_measured_by_display_width() {
+    let mut harness = Harness::new(App, 20, 4);
+    harness.click_text("next").click_text("next").click_text("next");
+
+    assert!(harness.row(0).starts_with("e\u{301}👍🏽!|"), "{harness}");
+    assert_eq!(harness.find_text("!|"), Some((3, 0)), "{harness}");
+}
+
+#[test]
+fn redrawing_over_wide_graphemes_matches_a_fresh_draw() {
+    let mut harness = Harness::new(App, 20, 4);
+
+    for step in 1..=TEXTS.len() {
+        harness.click_text("next");
+        assert_eq!(harness.row(0), fresh_row(step), "step {step}\n{harness}");
+    }
+}